@@ -0,0 +1,57 @@
+//! Command substitution
+
+use std::process::{Command, Stdio};
+
+///Runs command line using system shell, returning its stdout without trailing line breaks
+pub fn shell(command: &str) -> Result<String, String> {
+    let (program, flag) = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+    let output = match Command::new(program).args([flag, command]).stdin(Stdio::null()).output() {
+        Ok(output) => output,
+        Err(error) => return Err(format!("$({command}): Cannot run '{program}': {error}")),
+    };
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("$({command}): '{program}' failed ({}): {}", output.status, stderr.trim()));
+    }
+
+    match String::from_utf8(output.stdout) {
+        Ok(output) => Ok(output.trim_end_matches(['\r', '\n']).to_owned()),
+        Err(_) => Err(format!("$({command}): Output is not valid unicode")),
+    }
+}
+
+///Replaces every `$(command)` within value with output of command
+pub fn substitute(value: &str) -> Result<String, String> {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(start) = rest.find("$(") {
+        result.push_str(&rest[..start]);
+        let command = &rest[start + 2..];
+
+        //Parentheses can be nested within command
+        let mut depth = 0usize;
+        let end = command.char_indices().find(|(_, ch)| match ch {
+            '(' => {
+                depth += 1;
+                false
+            },
+            ')' if depth == 0 => true,
+            ')' => {
+                depth -= 1;
+                false
+            },
+            _ => false,
+        });
+        match end {
+            Some((end, _)) => {
+                result.push_str(&shell(&command[..end])?);
+                rest = &command[end + 1..];
+            },
+            None => return Err(format!("Missing closing parenthesis in '{value}'")),
+        }
+    }
+
+    result.push_str(rest);
+    Ok(result)
+}
@@ -0,0 +1,68 @@
+//! docker-compose `env_file` parser
+//!
+//! Matches docker-compose rules: no quote processing, no interpolation and empty values are allowed.
+
+use std::sync::Arc;
+
+use crate::{Entry, Error, Errors, Lines, Op, Options};
+
+///Parses lines of file as they are read, skipping variables that are not kept by `options`
+///
+///Variable without `=` is returned with `Op::Inherit` and empty value, to be taken from process environment by caller.
+pub fn parse_lines(name: &str, lines: &mut Lines<'_>, options: &Options) -> Result<Vec<Entry>, Errors> {
+    let file: Arc<str> = Arc::from(name);
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    for (idx, line) in lines.enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                errors.push(Error::new(name, None, format!("Read fail: {error}")));
+                break;
+            },
+        };
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value, op) = match line.split_once('=') {
+            Some((key, value)) => (key, value.to_owned(), Op::Set),
+            None => (line.trim_end(), String::new(), Op::Inherit),
+        };
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            errors.push(Error::new(name, Some(idx + 1), format!("Invalid variable name '{key}'")));
+            continue;
+        }
+        if !options.keeps(key) {
+            continue;
+        }
+
+        let mut entry = Entry::new(key.to_owned(), value, &file, idx + 1);
+        entry.op = op;
+        entries.push(entry);
+    }
+
+    match errors.len() {
+        0 => Ok(entries),
+        _ => Err(Errors(errors)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::borrow::Cow;
+
+    fn parse_str(text: &str, options: &Options) -> Result<Vec<Entry>, Errors> {
+        parse_lines("compose.env", &mut text.lines().map(|line| Ok(Cow::Borrowed(line))), options)
+    }
+
+    #[test]
+    fn should_leave_variable_without_value_to_caller() {
+        let entries = parse_str("A='quoted'\nPATH\nB=\n", &Options::default()).unwrap();
+        let result: Vec<_> = entries.iter().map(|entry| (entry.key.as_str(), entry.value.as_str(), entry.op)).collect();
+        assert_eq!(result, [("A", "'quoted'", Op::Set), ("PATH", "", Op::Inherit), ("B", "", Op::Set)]);
+    }
+}
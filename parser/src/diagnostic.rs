@@ -0,0 +1,22 @@
+//! Non-fatal diagnostics
+//!
+//! Printed to stderr, which is shown by cargo for proc macros and build scripts.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+static EMITTED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+///Emits warning only once per process
+pub fn warn_once(message: String) {
+    let mut emitted = match EMITTED.lock() {
+        Ok(emitted) => emitted,
+        Err(error) => error.into_inner(),
+    };
+
+    let emitted = emitted.get_or_insert_with(HashSet::new);
+    if !emitted.contains(&message) {
+        eprintln!("warning: env-smart: {message}");
+        emitted.insert(message);
+    }
+}
@@ -0,0 +1,315 @@
+//! JSON env files
+//!
+//! Top level object maps names to values, while nested objects are flattened with dots: `{"database": {"url": "..."}}` defines `database.url`.
+//! Numbers and booleans are used as written, arrays of scalars define arrays and `null` leaves variable undefined.
+
+use core::cell::Cell;
+use std::sync::Arc;
+
+use crate::{Entry, Error, Errors, Op};
+
+//Checks number grammar of JSON: `-? (0 | [1-9][0-9]*) (.[0-9]+)? ([eE][+-]?[0-9]+)?`
+fn is_number(literal: &str) -> bool {
+    fn digits(text: &str) -> (&str, &str) {
+        let len = text.find(|ch: char| !ch.is_ascii_digit()).unwrap_or(text.len());
+        text.split_at(len)
+    }
+
+    let text = literal.strip_prefix('-').unwrap_or(literal);
+    let (integer, mut rest) = digits(text);
+    if integer.is_empty() || (integer.len() > 1 && integer.starts_with('0')) {
+        return false;
+    }
+    if let Some(fraction) = rest.strip_prefix('.') {
+        let (fraction, tail) = digits(fraction);
+        if fraction.is_empty() {
+            return false;
+        }
+        rest = tail;
+    }
+    if let Some(exponent) = rest.strip_prefix(['e', 'E']) {
+        let (exponent, tail) = digits(exponent.strip_prefix(['+', '-']).unwrap_or(exponent));
+        if exponent.is_empty() {
+            return false;
+        }
+        rest = tail;
+    }
+    rest.is_empty()
+}
+
+struct Parser<'a> {
+    name: &'a str,
+    //Name shared by entries
+    file: Arc<str>,
+    text: &'a str,
+    pos: usize,
+    //Last computed line with its position, so that lines of large file are not counted from its start every time
+    line_at: Cell<(usize, usize)>,
+}
+
+impl<'a> Parser<'a> {
+    fn line(&self) -> usize {
+        let (mut pos, mut line) = self.line_at.get();
+        if pos > self.pos {
+            (pos, line) = (0, 1);
+        }
+        line += self.text[pos..self.pos].matches('\n').count();
+        self.line_at.set((self.pos, line));
+        line
+    }
+
+    fn error(&self, message: &str) -> Error {
+        Error::new(self.name, Some(self.line()), message.to_owned())
+    }
+
+    fn rest(&self) -> &'a str {
+        &self.text[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start_matches([' ', '\t', '\r', '\n']).len();
+    }
+
+    fn peek(&mut self) -> Option<char> {
+        self.skip_whitespace();
+        self.rest().chars().next()
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), Error> {
+        match self.peek() {
+            Some(ch) if ch == expected => {
+                self.pos += ch.len_utf8();
+                Ok(())
+            },
+            Some(ch) => Err(self.error(&format!("Expected '{expected}', got '{ch}'"))),
+            None => Err(self.error(&format!("Expected '{expected}', got end of file"))),
+        }
+    }
+
+    fn hex(&mut self) -> Result<u32, Error> {
+        let digits = self.rest().get(..4).filter(|digits| digits.chars().all(|ch| ch.is_ascii_hexdigit()));
+        match digits {
+            Some(digits) => {
+                self.pos += 4;
+                Ok(u32::from_str_radix(digits, 16).unwrap_or_default())
+            },
+            None => Err(self.error("Expected 4 hex digits of unicode escape")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, Error> {
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            let mut chars = self.rest().chars();
+            let ch = match chars.next() {
+                Some(ch) => ch,
+                None => return Err(self.error("Missing closing quote")),
+            };
+            self.pos += ch.len_utf8();
+            match ch {
+                '"' => return Ok(result),
+                '\\' => {
+                    let escape = chars.next().ok_or_else(|| self.error("Missing closing quote"))?;
+                    self.pos += escape.len_utf8();
+                    match escape {
+                        '"' | '\\' | '/' => result.push(escape),
+                        'b' => result.push('\u{8}'),
+                        'f' => result.push('\u{c}'),
+                        'n' => result.push('\n'),
+                        'r' => result.push('\r'),
+                        't' => result.push('\t'),
+                        'u' => {
+                            let mut code = self.hex()?;
+                            //Surrogate pair
+                            if (0xD800..0xDC00).contains(&code) && self.rest().starts_with("\\u") {
+                                self.pos += 2;
+                                let low = self.hex()?;
+                                if !(0xDC00..0xE000).contains(&low) {
+                                    return Err(self.error("Invalid low surrogate of unicode escape"));
+                                }
+                                code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                            }
+                            result.push(char::from_u32(code).ok_or_else(|| self.error("Invalid unicode escape"))?);
+                        },
+                        _ => return Err(self.error(&format!("Invalid escape '\\{escape}'"))),
+                    }
+                },
+                ch if ch.is_control() => return Err(self.error("Control character within string")),
+                ch => result.push(ch),
+            }
+        }
+    }
+
+    //Parses number, `true`, `false` or `null`, returning `None` for `null`
+    fn literal(&mut self) -> Result<Option<String>, Error> {
+        let rest = self.rest();
+        let len = rest.find(|ch: char| !(ch.is_ascii_alphanumeric() || matches!(ch, '-' | '+' | '.'))).unwrap_or(rest.len());
+        let literal = &rest[..len];
+        match literal {
+            "null" => (),
+            "true" | "false" => (),
+            _ if is_number(literal) => (),
+            "" => return Err(self.error("Expected value")),
+            _ => return Err(self.error(&format!("Unexpected '{literal}'"))),
+        }
+        self.pos += len;
+        Ok(Some(literal.to_owned()).filter(|literal| literal != "null"))
+    }
+
+    fn scalar(&mut self) -> Result<Option<String>, Error> {
+        match self.peek() {
+            Some('"') => self.string().map(Some),
+            Some('{' | '[') => Err(self.error("Array can only contain strings, numbers and booleans")),
+            _ => self.literal(),
+        }
+    }
+
+    fn array(&mut self, key: &str, entries: &mut Vec<Entry>) -> Result<(), Error> {
+        self.expect('[')?;
+        if self.peek() == Some(']') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            let line = self.line();
+            if let Some(value) = self.scalar()? {
+                let mut entry = Entry::new(key.to_owned(), value, &self.file, line);
+                entry.op = Op::Push;
+                entries.push(entry);
+            }
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                _ => return self.expect(']'),
+            }
+        }
+    }
+
+    fn object(&mut self, prefix: &str, entries: &mut Vec<Entry>) -> Result<(), Error> {
+        self.expect('{')?;
+        if self.peek() == Some('}') {
+            self.pos += 1;
+            return Ok(());
+        }
+        loop {
+            self.skip_whitespace();
+            let line = self.line();
+            let key = format!("{prefix}{}", self.string()?);
+            if key.is_empty() {
+                return Err(self.error("Variable name cannot be empty"));
+            }
+            self.expect(':')?;
+            match self.peek() {
+                Some('{') => self.object(&format!("{key}."), entries)?,
+                Some('[') => self.array(&key, entries)?,
+                _ => if let Some(value) = self.scalar()? {
+                    entries.push(Entry::new(key, value, &self.file, line));
+                },
+            }
+            match self.peek() {
+                Some(',') => self.pos += 1,
+                _ => return self.expect('}'),
+            }
+        }
+    }
+}
+
+///Parses content of file
+pub fn parse_str(name: &str, text: &str) -> Result<Vec<Entry>, Errors> {
+    let mut parser = Parser {
+        name,
+        file: Arc::from(name),
+        text,
+        pos: 0,
+        line_at: Cell::new((0, 1)),
+    };
+    let mut entries = Vec::new();
+    if parser.peek() != Some('{') {
+        return Err(parser.error("Expected object of variables").into());
+    }
+    parser.object("", &mut entries)?;
+    match parser.peek() {
+        None => Ok(entries),
+        Some(_) => Err(parser.error("Unexpected content after object").into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str) -> Vec<(String, String, Op)> {
+        parse_str("env.json", text).unwrap().into_iter().map(|entry| (entry.key, entry.value, entry.op)).collect()
+    }
+
+    fn set(key: &str, value: &str) -> (String, String, Op) {
+        (key.to_owned(), value.to_owned(), Op::Set)
+    }
+
+    #[test]
+    fn should_flatten_nested_objects() {
+        let result = values(r#"{
+            "database": {"url": "postgres://localhost", "pool": {"size": 8}},
+            "debug": false,
+            "missing": null,
+            "empty": {},
+            "hosts": ["a", 1, null]
+        }"#);
+        let push = |value: &str| ("hosts".to_owned(), value.to_owned(), Op::Push);
+        assert_eq!(result, [set("database.url", "postgres://localhost"), set("database.pool.size", "8"), set("debug", "false"), push("a"), push("1")]);
+    }
+
+    #[test]
+    fn should_unescape_strings() {
+        let result = values(r#"{"A": "q\"b\\s\/n\nt\t", "B": "\u00e9\ud83d\ude00", "\u0043": "key"}"#);
+        assert_eq!(result, [set("A", "q\"b\\s/n\nt\t"), set("B", "\u{e9}\u{1F600}"), set("C", "key")]);
+    }
+
+    #[test]
+    fn should_accept_json_numbers() {
+        for number in ["0", "-0", "12", "-1.5", "1e10", "2.5E-3", "1E+2"] {
+            let result = values(&format!(r#"{{"N": {}}}"#, number));
+            assert_eq!(result, [set("N", number)]);
+        }
+    }
+
+    #[test]
+    fn should_reject_invalid_document() {
+        let invalid = [
+            "",
+            "[]",
+            r#"{"A": -inf}"#,
+            r#"{"A": inf}"#,
+            r#"{"A": NaN}"#,
+            r#"{"A": 01}"#,
+            r#"{"A": 1.}"#,
+            r#"{"A": .5}"#,
+            r#"{"A": 1e}"#,
+            r#"{"A": +1}"#,
+            r#"{"A": 0x10}"#,
+            r#"{"A": "\ud83d"}"#,
+            r#"{"A": "\ud83d\u0041"}"#,
+            r#"{"A": "\ude00"}"#,
+            r#"{"A": "\u12"}"#,
+            r#"{"A": "\x41"}"#,
+            "{\"A\": \"line\nbreak\"}",
+            r#"{"A": "unterminated}"#,
+            r#"{"A": 1,}"#,
+            r#"{"A" 1}"#,
+            r#"{"": 1}"#,
+            r#"{"A": [[1]]}"#,
+            r#"{"A": [1}"#,
+            r#"{"A": 1} {}"#,
+        ];
+        for text in invalid {
+            assert!(parse_str("env.json", text).is_err(), "{:?} must be rejected", text);
+        }
+    }
+
+    #[test]
+    fn should_report_line_of_error() {
+        let error = parse_str("env.json", "{\n\"A\": 1,\n\"B\": -inf\n}").unwrap_err();
+        assert_eq!(error.0[0].line, Some(3));
+    }
+}
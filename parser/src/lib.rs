@@ -0,0 +1,837 @@
+//! Parser of env files used by [env-smart](https://crates.io/crates/env-smart) macros
+//!
+//! It follows exactly the same rules as macros, so build scripts and tests can read env files the same way.
+//!
+//! ```rust
+//! use env_smart_parser::{parse_str, KeyNames, Op, Options};
+//!
+//! let entries = parse_str(".env", "NAME=app # comment\nPATH+=:/opt/bin", &Options::default()).unwrap();
+//! assert_eq!(entries[0].key, "NAME");
+//! assert_eq!(entries[0].value, "app");
+//! assert_eq!(entries[1].op, Op::Append);
+//!
+//! let errors = parse_str(".env", "NAME=app\nBROKEN\n1KEY=value", &Options { key_names: KeyNames::Portable, ..Options::default() }).unwrap_err();
+//! assert_eq!(errors.0[0].line, Some(2));
+//! assert_eq!(errors.0[1].line, Some(3));
+//! ```
+//!
+//! ## Features
+//!
+//! - `commands` - Allow `$(command)` substitution within env files that opt in with `# env-smart: allow-commands` line.
+//! - `yaml` - Support YAML env files.
+
+#![warn(missing_docs)]
+#![allow(clippy::style)]
+
+use core::fmt;
+use std::borrow::Cow;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod compose;
+mod systemd;
+mod json;
+#[cfg(feature = "yaml")]
+mod yaml;
+mod diagnostic;
+#[cfg(feature = "commands")]
+mod command;
+
+const QUOTES: &[char] = &['"', '\''];
+//Marker line that allows command substitution within file
+const ALLOW_COMMANDS: &str = "# env-smart: allow-commands";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Syntax of plain env files
+pub enum Format {
+    ///`.env` syntax
+    Dotenv,
+    ///docker-compose `env_file` syntax
+    Compose,
+    ///systemd `EnvironmentFile` syntax
+    Systemd,
+    ///JSON object, with nested objects flattened to dotted names
+    Json,
+    #[cfg(feature = "yaml")]
+    ///YAML mapping, with nested mappings flattened to dotted names
+    Yaml,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Operation of entry
+pub enum Op {
+    ///`KEY=value`
+    Set,
+    ///`KEY+=value`, appends to earlier definition or process environment
+    Append,
+    ///`!KEY` or `unset KEY`, removes variable from files and process environment
+    Unset,
+    ///`KEY[]=value`, adds item to array
+    Push,
+    ///`KEY` without value of docker-compose file, takes value from process environment or is omitted
+    Inherit,
+}
+
+#[derive(Debug)]
+///Variable definition within file
+pub struct Entry {
+    ///Name of variable, prefixed by section if any
+    pub key: String,
+    ///Value without quotes and comment
+    pub value: String,
+    ///Operation on variable
+    pub op: Op,
+    ///Name of file, where variable is defined, shared by all entries of file
+    pub file: Arc<str>,
+    ///Line of file, where variable is defined
+    pub line: usize,
+    ///Cargo feature, which is required by `[feature:name]` section containing variable
+    pub feature: Option<String>,
+}
+
+impl Entry {
+    #[inline]
+    ///Creates `KEY=value` entry, defined at `line` of `file`
+    pub fn new(key: String, value: String, file: &Arc<str>, line: usize) -> Self {
+        Self {
+            key,
+            value,
+            op: Op::Set,
+            file: file.clone(),
+            line,
+            feature: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Policy of allowed variable names
+pub enum KeyNames {
+    ///Anything goes
+    Any,
+    ///ASCII letters, digits and `_`, not starting with digit. Sections can add `.`
+    Portable,
+    ///Same as `Portable`, but without lower case letters
+    Uppercase,
+}
+
+impl KeyNames {
+    ///Checks key, returning reason if it is not allowed
+    pub fn check(self, key: &str) -> Result<(), String> {
+        if self == Self::Any {
+            return Ok(());
+        }
+
+        if key.is_empty() {
+            return Err("it is empty".to_owned());
+        }
+        for part in key.split('.') {
+            if part.starts_with(|ch: char| ch.is_ascii_digit()) {
+                return Err("it starts with digit".to_owned());
+            }
+            for ch in part.chars() {
+                let reason = if ch.is_whitespace() {
+                    "it contains whitespace"
+                } else if !ch.is_ascii() {
+                    "it contains non-ASCII character"
+                } else if !(ch.is_ascii_alphanumeric() || ch == '_') {
+                    return Err(format!("it contains '{ch}'"));
+                } else if self == Self::Uppercase && ch.is_ascii_lowercase() {
+                    "it contains lower case letter"
+                } else {
+                    continue;
+                };
+                return Err(reason.to_owned());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+///Options of `.env` syntax
+pub struct Options {
+    ///Whether to trim whitespace around keys and values
+    pub trim: bool,
+    ///Allowed variable names
+    pub key_names: KeyNames,
+    ///Whether trailing backslash continues value on next line
+    ///
+    ///Disabled by default, as Windows paths such as `C:\tools\` end with backslash.
+    pub continuation: bool,
+    ///Glob patterns of variables to keep, where `*` stands for any sequence of characters
+    ///
+    ///Other variables are skipped while parsing, so their values are never stored. Empty list keeps every variable.
+    pub keys: Vec<String>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            trim: true,
+            key_names: KeyNames::Any,
+            continuation: false,
+            keys: Vec::new(),
+        }
+    }
+}
+
+impl Options {
+    #[inline]
+    ///Returns whether variable is kept according to `keys`
+    pub fn keeps(&self, key: &str) -> bool {
+        self.keys.is_empty() || self.keys.iter().any(|pattern| glob_match(pattern, key))
+    }
+}
+
+///Matches `text` against glob `pattern` where `*` stands for any sequence of characters
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    //First part must be prefix
+    let mut text = match parts.next() {
+        Some(prefix) => match text.strip_prefix(prefix) {
+            Some(text) => text,
+            None => return false,
+        },
+        None => return text.is_empty(),
+    };
+
+    let mut parts = parts.peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            //Last part must be suffix
+            return text.ends_with(part);
+        }
+
+        match text.find(part) {
+            Some(idx) => text = &text[idx + part.len()..],
+            None => return false,
+        }
+    }
+
+    //No `*` in pattern
+    text.is_empty()
+}
+
+#[derive(Debug, Clone)]
+///Error of parsing file
+pub struct Error {
+    ///Name of file
+    pub file: String,
+    ///Line of file where error occurred, if it is specific to line
+    pub line: Option<usize>,
+    ///Description of error
+    pub message: String,
+}
+
+impl Error {
+    #[inline]
+    fn new(file: &str, line: Option<usize>, message: String) -> Self {
+        Self {
+            file: file.to_owned(),
+            line,
+            message,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => fmt.write_fmt(format_args!("{}:{line}: {}", self.file, self.message)),
+            None => fmt.write_fmt(format_args!("{}: {}", self.file, self.message)),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+#[derive(Debug, Clone)]
+///Every error of parsing file, in order of occurrence
+pub struct Errors(pub Vec<Error>);
+
+impl From<Error> for Errors {
+    #[inline]
+    fn from(error: Error) -> Self {
+        Self(vec![error])
+    }
+}
+
+impl fmt::Display for Errors {
+    ///Displays each error on separate line
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, error) in self.0.iter().enumerate() {
+            if idx > 0 {
+                fmt.write_str("\n")?;
+            }
+            fmt::Display::fmt(error, fmt)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for Errors {}
+
+impl From<Errors> for String {
+    #[inline]
+    fn from(errors: Errors) -> Self {
+        errors.to_string()
+    }
+}
+
+//Returns whether `text` is nothing but optional comment
+fn is_comment(text: &str) -> bool {
+    let text = text.trim_start();
+    text.is_empty() || text.starts_with('#')
+}
+
+//Strips quotes or trailing comment from value
+fn parse_value(value: &str) -> &str {
+    if let Some(quote) = value.chars().next().filter(|ch| QUOTES.contains(ch)) {
+        //Comment can follow closing quote
+        match value.rfind(quote) {
+            Some(end) if end > 0 && is_comment(&value[end + 1..]) => return &value[1..end],
+            _ => return value.trim_matches(QUOTES),
+        }
+    }
+
+    //Comment starts with `#` at the beginning or after whitespace
+    let comment = value.char_indices().find(|(idx, ch)| *ch == '#' && value[..*idx].chars().next_back().map_or(true, char::is_whitespace));
+    match comment {
+        Some((idx, _)) => value[..idx].trim_end(),
+        None => value,
+    }
+}
+
+///Parses content of `.env` file, without support for include directives
+///
+///`name` of file is used in errors.
+pub fn parse_str(name: &str, text: &str, options: &Options) -> Result<Vec<Entry>, Errors> {
+    parse(name, &mut text.lines().map(|line| Ok(Cow::Borrowed(line))), options, &mut |_| None)
+}
+
+//Returns name of `[section]` header
+fn section_header(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let section = line.strip_prefix('[')?.strip_suffix(']')?;
+    if section.contains('=') {
+        None
+    } else {
+        Some(section.trim())
+    }
+}
+
+//Handler of include directive, returning `None` if they are not supported
+type Include<'a> = dyn FnMut(&str) -> Option<Result<Vec<Entry>, Errors>> + 'a;
+
+//Lines of file without line breaks
+pub(crate) type Lines<'a> = dyn Iterator<Item = io::Result<Cow<'a, str>>> + 'a;
+
+//Parses lines of file, passing include directives to `include`
+//
+//Malformed lines are skipped, so every error of file is reported at once.
+fn parse(name: &str, lines: &mut Lines<'_>, options: &Options, include: &mut Include<'_>) -> Result<Vec<Entry>, Errors> {
+    let file: Arc<str> = Arc::from(name);
+    let mut entries = Vec::<Entry>::new();
+    let mut errors = Vec::new();
+    //Prefix of keys within current section
+    let mut prefix = String::new();
+    //Feature required by current section
+    let mut feature = None;
+    let mut allow_commands = false;
+    let mut lines = lines.enumerate();
+    while let Some((idx, line)) = lines.next() {
+        let line_number = idx + 1;
+        let mut line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                errors.push(Error::new(name, None, format!("Read fail: {error}")));
+                break;
+            },
+        };
+        //Trailing backslash continues value on next line
+        while let Some(head) = line.strip_suffix('\\').filter(|_| options.continuation) {
+            let mut joined = head.to_owned();
+            match lines.next() {
+                Some((_, Ok(next))) => joined.push_str(&next),
+                Some((_, Err(error))) => {
+                    errors.push(Error::new(name, None, format!("Read fail: {error}")));
+                    line = Cow::Owned(joined);
+                    break;
+                },
+                None => {
+                    line = Cow::Owned(joined);
+                    break;
+                },
+            }
+            line = Cow::Owned(joined);
+        }
+        let line = line.as_ref();
+
+        if line.trim() == ALLOW_COMMANDS {
+            if !cfg!(feature = "commands") {
+                diagnostic::warn_once(format!("{name}: Command substitution requires `commands` feature"));
+            }
+            allow_commands = true;
+            continue;
+        }
+
+        if let Some(included) = include_directive(line).and_then(|path| include(path)) {
+            match included {
+                Ok(included) => entries.extend(included),
+                Err(Errors(included)) => errors.extend(included.into_iter().map(|mut error| {
+                    //Failure to include is error of directive
+                    if error.file == name && error.line.is_none() {
+                        error.line = Some(line_number);
+                    }
+                    error
+                })),
+            }
+            continue;
+        }
+
+        //Directives above also start with `#`, so comments are skipped after them
+        if is_comment(line) {
+            continue;
+        }
+
+        if let Some(section) = section_header(line) {
+            prefix.clear();
+            feature = None;
+            if let Some(name) = section.strip_prefix("feature:") {
+                feature = Some(name.trim().to_owned());
+            } else if !section.is_empty() {
+                prefix.push_str(section);
+                prefix.push('.');
+            }
+            continue;
+        }
+
+        if let Some(key) = line.strip_prefix('!').or_else(|| line.strip_prefix("unset ")) {
+            entries.push(Entry {
+                key: format!("{prefix}{}", key.trim()),
+                value: String::new(),
+                op: Op::Unset,
+                file: file.clone(),
+                line: line_number,
+                feature: feature.clone(),
+            });
+            continue;
+        }
+
+        let mut split = line.splitn(2, '=');
+        let mut key = split.next().unwrap();
+        let value = match split.next() {
+            Some(value) if options.trim => parse_value(value.trim()),
+            Some(value) => parse_value(value),
+            None => {
+                errors.push(Error::new(name, Some(line_number), format!("'{key}' has no value")));
+                continue;
+            },
+        };
+        if options.trim {
+            key = key.trim();
+        }
+
+        let (key, op) = if let Some(key) = key.strip_suffix('+') {
+            (key, Op::Append)
+        } else if let Some(key) = key.strip_suffix("[]") {
+            (key, Op::Push)
+        } else {
+            (key, Op::Set)
+        };
+        let key = if options.trim { key.trim_end() } else { key };
+        let key = format!("{prefix}{key}");
+        if let Err(error) = options.key_names.check(&key) {
+            errors.push(Error::new(name, Some(line_number), format!("Invalid variable name '{key}': {error}")));
+            continue;
+        }
+        //Skipped before command substitution, so that its value is never produced
+        if !options.keeps(&key) {
+            continue;
+        }
+        let value = match allow_commands {
+            #[cfg(feature = "commands")]
+            true => match command::substitute(value) {
+                Ok(value) => value,
+                Err(error) => {
+                    errors.push(Error::new(name, Some(line_number), format!("{key}: {error}")));
+                    continue;
+                },
+            },
+            _ => value.to_owned(),
+        };
+        entries.push(Entry {
+            key,
+            value,
+            op,
+            file: file.clone(),
+            line: line_number,
+            feature: feature.clone(),
+        });
+    }
+
+    match errors.len() {
+        0 => Ok(entries),
+        _ => Err(Errors(errors)),
+    }
+}
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16_LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16_BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> io::Result<String> {
+    let units = bytes.chunks(2).map(|unit| match unit {
+        [first, second] => from_bytes([*first, *second]),
+        //Odd trailing byte cannot be valid
+        _ => 0xD800,
+    });
+    char::decode_utf16(units).collect::<Result<String, _>>().map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "File is not valid UTF-16"))
+}
+
+///Reads text file, stripping byte order mark and transcoding UTF-16 if it is present
+pub fn read_text(path: &Path) -> io::Result<String> {
+    let bytes = fs::read(path)?;
+    if let Some(bytes) = bytes.strip_prefix(UTF16_LE_BOM) {
+        decode_utf16(bytes, u16::from_le_bytes)
+    } else if let Some(bytes) = bytes.strip_prefix(UTF16_BE_BOM) {
+        decode_utf16(bytes, u16::from_be_bytes)
+    } else {
+        //Content is validated in place, as large generated file should not be copied
+        let bom = if bytes.starts_with(UTF8_BOM) { UTF8_BOM.len() } else { 0 };
+        let mut text = String::from_utf8(bytes).map_err(|_| invalid_utf8())?;
+        text.drain(..bom);
+        Ok(text)
+    }
+}
+
+fn invalid_utf8() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "File is not valid UTF-8, nor UTF-16 with byte order mark")
+}
+
+//Reads lines of text file one at a time, so that large file is never held in memory at once
+//
+//UTF-16 file is transcoded as whole, as it cannot be split on bytes.
+fn read_lines(path: &Path) -> io::Result<Box<Lines<'static>>> {
+    let mut reader = io::BufReader::new(fs::File::open(path)?);
+    let head = reader.fill_buf()?;
+    if head.starts_with(UTF16_LE_BOM) || head.starts_with(UTF16_BE_BOM) {
+        let text = read_text(path)?;
+        let lines: Vec<String> = text.lines().map(str::to_owned).collect();
+        return Ok(Box::new(lines.into_iter().map(|line| Ok(Cow::Owned(line)))));
+    }
+    if head.starts_with(UTF8_BOM) {
+        reader.consume(UTF8_BOM.len());
+    }
+    Ok(Box::new(reader.lines().map(|line| match line {
+        Ok(line) => Ok(Cow::Owned(line)),
+        Err(error) if error.kind() == io::ErrorKind::InvalidData => Err(invalid_utf8()),
+        Err(error) => Err(error),
+    })))
+}
+
+//Returns path of `#include path` or `source path` directive
+fn include_directive(line: &str) -> Option<&str> {
+    let line = line.trim();
+    let path = line.strip_prefix("#include ").or_else(|| line.strip_prefix("source "))?;
+    Some(path.trim().trim_matches(QUOTES))
+}
+
+fn display_chain(chain: &[PathBuf]) -> String {
+    chain.iter().map(|path| path.display().to_string()).collect::<Vec<_>>().join(" -> ")
+}
+
+//Parses `.env` syntax, following include directives relative to file's directory
+fn parse_includes(path: &Path, lines: &mut Lines<'_>, options: &Options, chain: &mut Vec<PathBuf>, included: &mut Vec<PathBuf>) -> Result<Vec<Entry>, Errors> {
+    let name = path.display().to_string();
+    let via = match chain.len() {
+        1 => None,
+        _ => Some(display_chain(chain)),
+    };
+
+    let result = parse(&name, lines, options, &mut |include| {
+        let include = path.parent().unwrap_or_else(|| Path::new("")).join(include);
+        chain.push(include);
+        let result = include_file(&name, options, chain, included);
+        chain.pop();
+        Some(result)
+    });
+
+    match (result, via) {
+        //Errors of included files already describe their chain
+        (Err(Errors(mut errors)), Some(via)) => {
+            for error in errors.iter_mut().filter(|error| error.file == name) {
+                error.message = format!("{} (included via {via})", error.message);
+            }
+            Err(Errors(errors))
+        },
+        (result, _) => result,
+    }
+}
+
+//Parses last file of `chain`, which is included by file `name`
+fn include_file(name: &str, options: &Options, chain: &mut Vec<PathBuf>, included: &mut Vec<PathBuf>) -> Result<Vec<Entry>, Errors> {
+    let include = chain[chain.len() - 1].clone();
+    let identity = |path: &Path| fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    if chain[..chain.len() - 1].iter().any(|prev| identity(prev) == identity(&include)) {
+        return Err(Error::new(name, None, format!("Include cycle: {}", display_chain(chain))).into());
+    }
+    included.push(include.clone());
+    let mut lines = match read_lines(&include) {
+        Ok(lines) => lines,
+        Err(error) => return Err(Error::new(name, None, format!("Cannot include {}: {error}", display_chain(chain))).into()),
+    };
+    parse_includes(&include, &mut lines, options, chain, included)
+}
+
+///Parses file in specified format, returning `None` if it doesn't exist
+///
+///Files included by `.env` syntax directives are added to `included`.
+pub fn parse_file(path: &Path, format: Format, options: &Options, included: &mut Vec<PathBuf>) -> Result<Option<Vec<Entry>>, Errors> {
+    let name = path.display().to_string();
+    let open_error = |error: io::Error| match error.kind() {
+        io::ErrorKind::NotFound => Ok(None),
+        io::ErrorKind::InvalidData => Err(Errors::from(Error::new(&name, None, format!("Read fail: {error}")))),
+        _ => Err(Error::new(&name, None, format!("Cannot open: {error}")).into()),
+    };
+
+    let parse_text: fn(&str, &str) -> Result<Vec<Entry>, Errors> = match format {
+        //`.env` files are parsed as they are read, as generated ones can be large
+        Format::Dotenv => {
+            let mut lines = match read_lines(path) {
+                Ok(lines) => lines,
+                Err(error) => return open_error(error),
+            };
+            return parse_includes(path, &mut lines, options, &mut vec![path.to_path_buf()], included).map(Some);
+        },
+        Format::Compose | Format::Systemd => {
+            let mut lines = match read_lines(path) {
+                Ok(lines) => lines,
+                Err(error) => return open_error(error),
+            };
+            let parse_lines = match format {
+                Format::Compose => compose::parse_lines,
+                _ => systemd::parse_lines,
+            };
+            return parse_lines(&name, &mut lines, options).map(Some);
+        },
+        //Structure of documents spans lines, so they are read whole
+        Format::Json => json::parse_str,
+        #[cfg(feature = "yaml")]
+        Format::Yaml => yaml::parse_str,
+    };
+    let text = match read_text(path) {
+        Ok(text) => text,
+        Err(error) => return open_error(error),
+    };
+    let mut entries = parse_text(&name, &text)?;
+    entries.retain(|entry| options.keeps(&entry.key));
+    Ok(Some(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str, options: &Options) -> Vec<(String, String)> {
+        parse_str(".env", text, options).unwrap().into_iter().map(|entry| (entry.key, entry.value)).collect()
+    }
+
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(key, value)| (key.to_string(), value.to_string())).collect()
+    }
+
+    #[test]
+    fn should_keep_trailing_backslash_by_default() {
+        let result = values("A=C:\\tools\\\nB=2", &Options::default());
+        assert_eq!(result, pairs(&[("A", "C:\\tools\\"), ("B", "2")]));
+    }
+
+    #[test]
+    fn should_continue_line_when_enabled() {
+        let options = Options {
+            continuation: true,
+            ..Options::default()
+        };
+        let result = values("ARGS=--verbose \\\n--color=always\nB=2", &options);
+        assert_eq!(result, pairs(&[("ARGS", "--verbose --color=always"), ("B", "2")]));
+    }
+
+    #[test]
+    fn should_skip_blank_lines_and_comments() {
+        let result = values("# database
+
+HOST=localhost
+   
+  # port of server
+PORT=8080  # local dev port
+", &Options::default());
+        assert_eq!(result, pairs(&[("HOST", "localhost"), ("PORT", "8080")]));
+    }
+
+    #[test]
+    fn should_trim_tabs_around_equal_sign() {
+        assert_eq!(values("KEY\t=\tvalue\t", &Options::default()), pairs(&[("KEY", "value")]));
+        assert_eq!(values("KEY\t+=\t:/bin", &Options::default()), pairs(&[("KEY", ":/bin")]));
+    }
+
+    #[test]
+    fn should_preserve_tabs_without_trim() {
+        let options = Options {
+            trim: false,
+            ..Options::default()
+        };
+        assert_eq!(values("KEY\t=\tvalue\t", &options), pairs(&[("KEY\t", "\tvalue\t")]));
+    }
+
+    #[test]
+    fn should_strip_crlf_line_endings() {
+        let text = "A=1\r\nB=two words\r\n\r\n# comment\r\nC=3\r\n";
+        let expected = pairs(&[("A", "1"), ("B", "two words"), ("C", "3")]);
+        assert_eq!(values(text, &Options::default()), expected);
+        let options = Options {
+            trim: false,
+            ..Options::default()
+        };
+        assert_eq!(values(text, &options), expected);
+    }
+
+    #[test]
+    fn should_keep_quoted_value_before_crlf() {
+        let text = "A=\" spaced \"\r\nB='a\tb'  # comment\r\nC=\"x\ty\"\r\n";
+        assert_eq!(values(text, &Options::default()), pairs(&[("A", " spaced "), ("B", "a\tb"), ("C", "x\ty")]));
+    }
+
+    #[test]
+    fn should_skip_variables_not_kept() {
+        let options = Options {
+            keys: vec!["APP_*".to_owned(), "PORT".to_owned()],
+            ..Options::default()
+        };
+        let entries = parse_str(".env", "APP_NAME=app\nUNUSED=1\nPORT=80\nPORTS=1\n!UNUSED\n[APP]\nNAME=section", &options).unwrap();
+        let result: Vec<_> = entries.iter().map(|entry| (entry.key.as_str(), entry.op)).collect();
+        assert_eq!(result, [("APP_NAME", Op::Set), ("PORT", Op::Set), ("UNUSED", Op::Unset)]);
+    }
+
+    #[test]
+    fn should_skip_variables_not_kept_in_every_format() {
+        let options = Options {
+            keys: vec!["KEPT*".to_owned()],
+            ..Options::default()
+        };
+        let files = [
+            (Format::Compose, "compose", "KEPT=1\nOTHER=2\n"),
+            (Format::Systemd, "systemd", "KEPT=1\nOTHER=\"2\"\n"),
+            (Format::Json, "json", r#"{"KEPT": {"inner": 1}, "OTHER": 2}"#),
+        ];
+        for (format, extension, content) in files {
+            let path = std::env::temp_dir().join(format!("env-smart-parser-keys-{}.{extension}", std::process::id()));
+            fs::write(&path, content).unwrap();
+            let result = parse_file(&path, format, &options, &mut Vec::new());
+            fs::remove_file(&path).unwrap();
+
+            let keys: Vec<_> = result.unwrap().unwrap().into_iter().map(|entry| entry.key).collect();
+            assert!(keys.len() == 1 && keys[0].starts_with("KEPT"), "{:?}: {:?}", format, keys);
+        }
+    }
+
+    #[test]
+    fn should_read_crlf_file_with_bom() {
+        let path = std::env::temp_dir().join(format!("env-smart-parser-crlf-{}.env", std::process::id()));
+        fs::write(&path, b"\xEF\xBB\xBFA=\"1\"\r\nB = 2\r\n").unwrap();
+        let result = parse_file(&path, Format::Dotenv, &Options::default(), &mut Vec::new());
+        fs::remove_file(&path).unwrap();
+
+        let result: Vec<_> = result.unwrap().unwrap().into_iter().map(|entry| (entry.key, entry.value, entry.line)).collect();
+        assert_eq!(result, [("A".to_owned(), "1".to_owned(), 1), ("B".to_owned(), "2".to_owned(), 2)]);
+    }
+
+    #[test]
+    fn should_match_glob_patterns() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*", "ANY"));
+        assert!(glob_match("*_TOKEN", "API_TOKEN"));
+        assert!(glob_match("CARGO_*", "CARGO_PKG_NAME"));
+        assert!(glob_match("CARGO_*_NAME", "CARGO_PKG_NAME"));
+        assert!(glob_match("*PKG*", "CARGO_PKG_NAME"));
+        assert!(glob_match("A**B", "AB"));
+        assert!(glob_match("KEY", "KEY"));
+        assert!(glob_match("", ""));
+    }
+
+    #[test]
+    fn should_not_match_glob_patterns() {
+        assert!(!glob_match("", "KEY"));
+        assert!(!glob_match("KEY", "KEY2"));
+        assert!(!glob_match("*_TOKEN", "API_TOKEN_ID"));
+        assert!(!glob_match("CARGO_*", "APP_CARGO_NAME"));
+        assert!(!glob_match("CARGO_*_NAME", "CARGO_NAME"));
+        assert!(!glob_match("A*A", "A"));
+        assert!(!glob_match("*PKG*", "CARGO_NAME"));
+    }
+
+    #[test]
+    fn should_parse_unset_directives() {
+        let entries = parse_str(".env", "A=1\n!A\nunset B\n!  C  \n[app]\n!NAME", &Options::default()).unwrap();
+        let result: Vec<_> = entries.iter().map(|entry| (entry.key.as_str(), entry.value.as_str(), entry.op, entry.line)).collect();
+        assert_eq!(result, [
+            ("A", "1", Op::Set, 1),
+            ("A", "", Op::Unset, 2),
+            ("B", "", Op::Unset, 3),
+            ("C", "", Op::Unset, 4),
+            ("app.NAME", "", Op::Unset, 6),
+        ]);
+    }
+
+    fn include_dir(name: &str, files: &[(&str, &str)]) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("env-smart-parser-{name}-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        for (file, content) in files {
+            fs::write(dir.join(file), content).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn should_follow_include_directives() {
+        let dir = include_dir("include", &[(".env", "A=1\n#include common.env\nC=3"), ("common.env", "source 'nested.env'\nB=2"), ("nested.env", "N=0")]);
+        let mut included = Vec::new();
+        let result = parse_file(&dir.join(".env"), Format::Dotenv, &Options::default(), &mut included);
+        let _ = fs::remove_dir_all(&dir);
+
+        let result: Vec<_> = result.unwrap().unwrap().into_iter().map(|entry| (entry.key, entry.value)).collect();
+        assert_eq!(result, pairs(&[("A", "1"), ("N", "0"), ("B", "2"), ("C", "3")]));
+        assert_eq!(included, [dir.join("common.env"), dir.join("nested.env")]);
+    }
+
+    #[test]
+    fn should_detect_include_cycle() {
+        let dir = include_dir("cycle", &[(".env", "A=1\n#include first.env"), ("first.env", "#include second.env"), ("second.env", "source ./first.env"), ("self.env", "#include self.env")]);
+        let cycle = parse_file(&dir.join(".env"), Format::Dotenv, &Options::default(), &mut Vec::new());
+        let itself = parse_file(&dir.join("self.env"), Format::Dotenv, &Options::default(), &mut Vec::new());
+        let from_middle = parse_file(&dir.join("first.env"), Format::Dotenv, &Options::default(), &mut Vec::new());
+        let _ = fs::remove_dir_all(&dir);
+
+        let errors = cycle.unwrap_err().0;
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.starts_with("Include cycle: "), "{}", errors[0].message);
+        assert!(errors[0].message.contains("first.env -> ") && errors[0].message.contains("second.env -> "), "{}", errors[0].message);
+        assert!(itself.unwrap_err().0[0].message.starts_with("Include cycle: "));
+        //Cycle is reported regardless of which file of it is loaded
+        assert!(from_middle.unwrap_err().0[0].message.starts_with("Include cycle: "));
+    }
+
+    #[test]
+    fn should_report_missing_include() {
+        let dir = include_dir("missing-include", &[(".env", "A=1\n#include missing.env")]);
+        let result = parse_file(&dir.join(".env"), Format::Dotenv, &Options::default(), &mut Vec::new());
+        let _ = fs::remove_dir_all(&dir);
+
+        let errors = result.unwrap_err().0;
+        assert_eq!(errors[0].line, Some(2));
+        assert!(errors[0].message.starts_with("Cannot include "), "{}", errors[0].message);
+    }
+}
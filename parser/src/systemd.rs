@@ -0,0 +1,175 @@
+//! systemd `EnvironmentFile` parser
+//!
+//! Follows systemd rules: `#` and `;` comments, backslash continuation lines and shell-like quoting.
+
+use std::sync::Arc;
+
+use crate::diagnostic;
+use crate::{Entry, Error, Errors, Lines, Options};
+
+//Characters that can be escaped within double quotes
+const DOUBLE_QUOTE_ESCAPES: &[char] = &['"', '\\', '`', '$'];
+
+fn parse_value(value: &str) -> Result<String, &'static str> {
+    let mut result = String::with_capacity(value.len());
+    //Length of value without trailing unquoted whitespace
+    let mut significant_len = 0;
+    let mut chars = value.trim_start().chars();
+
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\'' => loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some(ch) => result.push(ch),
+                    None => return Err("Missing closing single quote"),
+                }
+            },
+            '"' => loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some(ch) if DOUBLE_QUOTE_ESCAPES.contains(&ch) => result.push(ch),
+                        Some(ch) => {
+                            result.push('\\');
+                            result.push(ch);
+                        },
+                        None => return Err("Missing closing double quote"),
+                    },
+                    Some(ch) => result.push(ch),
+                    None => return Err("Missing closing double quote"),
+                }
+            },
+            '\\' => if let Some(ch) = chars.next() {
+                result.push(ch);
+            },
+            ch if ch.is_whitespace() => {
+                result.push(ch);
+                continue;
+            },
+            ch => result.push(ch),
+        }
+        significant_len = result.len();
+    }
+
+    result.truncate(significant_len);
+    Ok(result)
+}
+
+///Parses lines of file as they are read, skipping variables that are not kept by `options`
+pub fn parse_lines(name: &str, lines: &mut Lines<'_>, options: &Options) -> Result<Vec<Entry>, Errors> {
+    let file: Arc<str> = Arc::from(name);
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    let mut lines = lines.enumerate();
+
+    while let Some((idx, line)) = lines.next() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => {
+                errors.push(Error::new(name, None, format!("Read fail: {error}")));
+                break;
+            },
+        };
+        let line = line.trim_start();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+
+        let mut line = line.to_owned();
+        while line.ends_with('\\') {
+            line.pop();
+            match lines.next() {
+                Some((_, Ok(next))) => line.push_str(&next),
+                Some((_, Err(error))) => {
+                    errors.push(Error::new(name, None, format!("Read fail: {error}")));
+                    break;
+                },
+                None => break,
+            }
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) => (key.trim_end(), value),
+            None => {
+                diagnostic::warn_once(format!("{name}:{}: Line without '=' is ignored", idx + 1));
+                continue;
+            },
+        };
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            errors.push(Error::new(name, Some(idx + 1), format!("Invalid variable name '{key}'")));
+            continue;
+        }
+        if !options.keeps(key) {
+            continue;
+        }
+
+        match parse_value(value) {
+            Ok(value) => entries.push(Entry::new(key.to_owned(), value, &file, idx + 1)),
+            Err(error) => errors.push(Error::new(name, Some(idx + 1), format!("{key}: {error}"))),
+        }
+    }
+
+    match errors.len() {
+        0 => Ok(entries),
+        _ => Err(Errors(errors)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+
+    fn parse_str(text: &str) -> Result<Vec<(String, String, usize)>, Errors> {
+        let entries = parse_lines("app.env", &mut text.lines().map(|line| Ok(Cow::Borrowed(line))), &Options::default())?;
+        Ok(entries.into_iter().map(|entry| (entry.key, entry.value, entry.line)).collect())
+    }
+
+    fn values(text: &str) -> Vec<(String, String)> {
+        parse_str(text).unwrap().into_iter().map(|(key, value, _)| (key, value)).collect()
+    }
+
+    #[test]
+    fn should_unquote_values() {
+        assert_eq!(values("SINGLE='a \\\" b'\nDOUBLE=\"a \\\" \\$ \\n b\"\nMIXED=a' b 'c\"d\"\nPLAIN=  a b  \nESCAPED=a\\ \\b\\  \n"), [
+            ("SINGLE".to_owned(), "a \\\" b".to_owned()),
+            ("DOUBLE".to_owned(), "a \" $ \\n b".to_owned()),
+            ("MIXED".to_owned(), "a b cd".to_owned()),
+            ("PLAIN".to_owned(), "a b".to_owned()),
+            ("ESCAPED".to_owned(), "a b ".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn should_keep_whitespace_around_quoted_value() {
+        assert_eq!(values("KEY = ' a '  "), [("KEY".to_owned(), " a ".to_owned())]);
+    }
+
+    #[test]
+    fn should_join_continuation_lines() {
+        let entries = parse_str("MULTI=first \\\nsecond \\\n  third\nNEXT=value").unwrap();
+        assert_eq!(entries, [
+            ("MULTI".to_owned(), "first second   third".to_owned(), 1),
+            ("NEXT".to_owned(), "value".to_owned(), 4),
+        ]);
+        //Continuation at end of file
+        assert_eq!(values("KEY=value\\"), [("KEY".to_owned(), "value".to_owned())]);
+    }
+
+    #[test]
+    fn should_skip_comments() {
+        let entries = parse_str("# comment\n; comment\n  # indented\n\nKEY=value # not comment\nOTHER=a;b").unwrap();
+        assert_eq!(entries, [
+            ("KEY".to_owned(), "value # not comment".to_owned(), 5),
+            ("OTHER".to_owned(), "a;b".to_owned(), 6),
+        ]);
+    }
+
+    #[test]
+    fn should_report_malformed_lines() {
+        let errors = parse_str("A B=value\nOPEN='value\nDOUBLE=\"value\nOK=1").unwrap_err();
+        let lines: Vec<Option<usize>> = errors.0.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [Some(1), Some(2), Some(3)]);
+    }
+}
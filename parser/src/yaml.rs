@@ -0,0 +1,373 @@
+//! YAML env files
+//!
+//! Supports subset of YAML used for configuration:
+//!
+//! - Nested block mappings, which keys are flattened with dots. Indentation must use spaces.
+//! - Plain, single and double quoted scalars, with `#` comments after whitespace outside of quotes.
+//! - Block scalars: literal `|` keeps line breaks, folded `>` joins lines with spaces and blank lines into line breaks.
+//!   Final line break is kept, unless `-` indicator strips it. Other indicators are rejected.
+//! - Block (`- item`) and flow (`[a, b]`) sequences of scalars, which define arrays.
+//!
+//! `null` or `~` leaves variable undefined, while key without value or nested content defines empty variable.
+//! Anchors, aliases, tags and flow mappings are not supported.
+
+use std::sync::Arc;
+
+use crate::{Entry, Error, Errors, Op};
+
+//Strips comment, which starts with `#` at start of line or after whitespace outside of quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (idx, ch) in line.char_indices() {
+        match (quote, ch) {
+            (None, '"' | '\'') => quote = Some(ch),
+            (Some(open), ch) if ch == open && prev != '\\' => quote = None,
+            (None, '#') if prev.is_whitespace() => return &line[..idx],
+            _ => (),
+        }
+        prev = ch;
+    }
+    line
+}
+
+//Returns position of `:` separating key from value
+fn key_separator(line: &str) -> Option<usize> {
+    let mut quote = None;
+    for (idx, ch) in line.char_indices() {
+        match (quote, ch) {
+            (None, '"' | '\'') if idx == 0 => quote = Some(ch),
+            (Some(open), ch) if ch == open => quote = None,
+            (None, ':') if line[idx + 1..].is_empty() || line[idx + 1..].starts_with([' ', '\t']) => return Some(idx),
+            _ => (),
+        }
+    }
+    None
+}
+
+fn unquote_key(key: &str) -> &str {
+    let key = key.trim();
+    match (key.chars().next(), key.chars().last()) {
+        (Some(first @ ('"' | '\'')), Some(last)) if key.len() > 1 && first == last => &key[1..key.len() - 1],
+        _ => key,
+    }
+}
+
+//Parses scalar, returning `None` for null
+fn scalar(value: &str) -> Result<Option<String>, String> {
+    let value = value.trim();
+    if let Some(quoted) = value.strip_prefix('"') {
+        let quoted = quoted.strip_suffix('"').ok_or("Missing closing double quote")?;
+        let mut result = String::with_capacity(quoted.len());
+        let mut chars = quoted.chars();
+        while let Some(ch) = chars.next() {
+            if ch != '\\' {
+                result.push(ch);
+                continue;
+            }
+            match chars.next() {
+                Some('n') => result.push('\n'),
+                Some('t') => result.push('\t'),
+                Some('r') => result.push('\r'),
+                Some('0') => result.push('\0'),
+                Some(ch @ ('"' | '\\' | '/' | ' ')) => result.push(ch),
+                Some(ch) => return Err(format!("Unsupported escape '\\{ch}'")),
+                None => return Err("Missing closing double quote".to_owned()),
+            }
+        }
+        Ok(Some(result))
+    } else if let Some(quoted) = value.strip_prefix('\'') {
+        let quoted = quoted.strip_suffix('\'').ok_or("Missing closing single quote")?;
+        Ok(Some(quoted.replace("''", "'")))
+    } else if value.starts_with(['{', '&', '*', '!']) {
+        Err(format!("Unsupported value '{value}'"))
+    } else if matches!(value, "" | "~" | "null" | "Null" | "NULL") {
+        Ok(None)
+    } else {
+        Ok(Some(value.to_owned()))
+    }
+}
+
+//Items of `[a, b]` flow sequence
+fn flow_sequence(value: &str) -> Option<Result<Vec<Option<String>>, String>> {
+    let items = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+    if items.trim().is_empty() {
+        return Some(Ok(Vec::new()));
+    }
+    if items.contains(['[', '{', '"', '\'']) {
+        return Some(Err("Flow sequence can only contain plain scalars".to_owned()));
+    }
+    Some(items.split(',').map(scalar).collect())
+}
+
+//Folds lines of `>` block scalar, turning blank lines into line breaks
+fn fold(lines: &[&str]) -> String {
+    let mut result = String::new();
+    let mut line_start = true;
+    for line in lines {
+        if line.is_empty() {
+            result.push('\n');
+            line_start = true;
+        } else {
+            if !line_start {
+                result.push(' ');
+            }
+            result.push_str(line);
+            line_start = false;
+        }
+    }
+    result
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start_matches(' ').len()
+}
+
+///Parses content of file
+pub fn parse_str(name: &str, text: &str) -> Result<Vec<Entry>, Errors> {
+    let file: Arc<str> = Arc::from(name);
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    //Indentation of mappings with prefix of their keys
+    let mut mappings: Vec<(usize, String)> = Vec::new();
+    //Key without value, which is followed by nested mapping or sequence, with its line and whether it has items
+    let mut parent: Option<(usize, String, usize, bool)> = None;
+    let lines: Vec<&str> = text.lines().collect();
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let line_number = idx + 1;
+        let raw = lines[idx];
+        idx += 1;
+        if raw.trim_start_matches(' ').starts_with('\t') {
+            errors.push(Error::new(name, Some(line_number), "Tabs cannot be used for indentation".to_owned()));
+            continue;
+        }
+        let line = strip_comment(raw).trim_end();
+        let content = line.trim_start();
+        if content.is_empty() || content == "---" || content == "..." {
+            continue;
+        }
+        let indent = indent_of(line);
+
+        if content == "-" || content.starts_with("- ") {
+            match parent.as_mut() {
+                Some((parent_indent, key, _, has_items)) if indent >= *parent_indent => {
+                    *has_items = true;
+                    let item = content[1..].trim();
+                    if key_separator(item).is_some() || item.starts_with("- ") {
+                        errors.push(Error::new(name, Some(line_number), format!("{key}: Sequence can only contain scalars")));
+                        continue;
+                    }
+                    match scalar(item) {
+                        Ok(Some(value)) => {
+                            let mut entry = Entry::new(key.clone(), value, &file, line_number);
+                            entry.op = Op::Push;
+                            entries.push(entry);
+                        },
+                        Ok(None) => (),
+                        Err(error) => errors.push(Error::new(name, Some(line_number), format!("{key}: {error}"))),
+                    }
+                },
+                _ => errors.push(Error::new(name, Some(line_number), "Sequence item without key".to_owned())),
+            }
+            continue;
+        }
+
+        //Key without value opens nested mapping when followed by more indented keys, or defines empty variable
+        match parent.take() {
+            Some((parent_indent, key, _, false)) if indent > parent_indent => mappings.push((indent, format!("{key}."))),
+            Some((_, key, line, false)) => entries.push(Entry::new(key, String::new(), &file, line)),
+            Some((_, _, _, true)) | None => (),
+        }
+        while mappings.last().map_or(false, |(mapping_indent, _)| *mapping_indent > indent) {
+            mappings.pop();
+        }
+        match mappings.last() {
+            Some((mapping_indent, _)) if *mapping_indent == indent => (),
+            None => mappings.push((indent, String::new())),
+            Some(_) => {
+                errors.push(Error::new(name, Some(line_number), "Inconsistent indentation".to_owned()));
+                continue;
+            },
+        }
+        let prefix = mappings.last().map(|(_, prefix)| prefix.as_str()).unwrap_or_default();
+
+        let separator = match key_separator(content) {
+            Some(separator) => separator,
+            None => {
+                errors.push(Error::new(name, Some(line_number), format!("Expected 'key: value', got '{content}'")));
+                continue;
+            },
+        };
+        let key = unquote_key(&content[..separator]);
+        if key.is_empty() {
+            errors.push(Error::new(name, Some(line_number), "Variable name cannot be empty".to_owned()));
+            continue;
+        }
+        let key = format!("{prefix}{key}");
+        let value = content[separator + 1..].trim();
+
+        if value.is_empty() {
+            parent = Some((indent, key, line_number, false));
+            continue;
+        }
+
+        //Block scalar consists of following lines, which are more indented
+        if let Some(indicators) = value.strip_prefix(['|', '>']) {
+            if !matches!(indicators.trim(), "" | "-") {
+                errors.push(Error::new(name, Some(line_number), format!("{key}: Unsupported block scalar indicator '{}'", indicators.trim())));
+                continue;
+            }
+            let literal = value.starts_with('|');
+            let mut block = Vec::new();
+            while idx < lines.len() && (lines[idx].trim().is_empty() || indent_of(lines[idx]) > indent) {
+                block.push(lines[idx]);
+                idx += 1;
+            }
+            while block.last().map_or(false, |line| line.trim().is_empty()) {
+                block.pop();
+            }
+            let block_indent = block.iter().filter(|line| !line.trim().is_empty()).map(|line| indent_of(line)).min().unwrap_or_default();
+            let block: Vec<&str> = block.iter().map(|line| line.get(block_indent..).unwrap_or_default()).collect();
+            let mut value = match literal {
+                true => block.join("\n"),
+                false => fold(&block),
+            };
+            //Final line break is kept unless `-` indicator strips it
+            if !value.is_empty() && !indicators.contains('-') {
+                value.push('\n');
+            }
+            entries.push(Entry::new(key, value, &file, line_number));
+            continue;
+        }
+
+        if let Some(items) = flow_sequence(value) {
+            match items {
+                Ok(items) => for value in items.into_iter().flatten() {
+                    let mut entry = Entry::new(key.clone(), value, &file, line_number);
+                    entry.op = Op::Push;
+                    entries.push(entry);
+                },
+                Err(error) => errors.push(Error::new(name, Some(line_number), format!("{key}: {error}"))),
+            }
+            continue;
+        }
+
+        match scalar(value) {
+            Ok(Some(value)) => entries.push(Entry::new(key, value, &file, line_number)),
+            Ok(None) => (),
+            Err(error) => errors.push(Error::new(name, Some(line_number), format!("{key}: {error}"))),
+        }
+    }
+
+    if let Some((_, key, line, false)) = parent {
+        entries.push(Entry::new(key, String::new(), &file, line));
+    }
+
+    match errors.len() {
+        0 => Ok(entries),
+        _ => Err(Errors(errors)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str) -> Vec<(String, String, Op)> {
+        parse_str("env.yaml", text).unwrap().into_iter().map(|entry| (entry.key, entry.value, entry.op)).collect()
+    }
+
+    fn set(key: &str, value: &str) -> (String, String, Op) {
+        (key.to_owned(), value.to_owned(), Op::Set)
+    }
+
+    fn push(key: &str, value: &str) -> (String, String, Op) {
+        (key.to_owned(), value.to_owned(), Op::Push)
+    }
+
+    #[test]
+    fn should_flatten_nested_mappings() {
+        let result = values("
+database:
+  url: postgres://localhost # comment
+  pool:
+    size: 8
+  name: app
+debug: false
+");
+        assert_eq!(result, [set("database.url", "postgres://localhost"), set("database.pool.size", "8"), set("database.name", "app"), set("debug", "false")]);
+    }
+
+    #[test]
+    fn should_define_empty_variable_for_key_without_value() {
+        let result = values("empty:\nnext: 1\nnested:\n  inner:\nlast:");
+        assert_eq!(result, [set("empty", ""), set("next", "1"), set("nested.inner", ""), set("last", "")]);
+        assert_eq!(values("a: ~\nb: null"), []);
+    }
+
+    #[test]
+    fn should_parse_quoted_scalars() {
+        let result = values(r#"
+double: "a # not comment\tb \"q\""
+single: 'it''s # here'
+plain: value # comment
+hash: a#b
+"quoted key": 1
+"#);
+        assert_eq!(result, [set("double", "a # not comment\tb \"q\""), set("single", "it's # here"), set("plain", "value"), set("hash", "a#b"), set("quoted key", "1")]);
+    }
+
+    #[test]
+    fn should_parse_block_scalars() {
+        let result = values("
+literal: |
+  first
+    indented
+
+  # kept
+folded: >
+  joined
+  line
+
+  next paragraph
+stripped: |-
+  no newline
+after: 1
+");
+        assert_eq!(result, [
+            set("literal", "first\n  indented\n\n# kept\n"),
+            set("folded", "joined line\nnext paragraph\n"),
+            set("stripped", "no newline"),
+            set("after", "1"),
+        ]);
+    }
+
+    #[test]
+    fn should_parse_sequences() {
+        let result = values("hosts:\n  - a\n  - 'b'\n  - ~\nflow: [x, y]\nafter: 1");
+        assert_eq!(result, [push("hosts", "a"), push("hosts", "b"), push("flow", "x"), push("flow", "y"), set("after", "1")]);
+    }
+
+    #[test]
+    fn should_reject_unsupported_syntax() {
+        let invalid = [
+            "key: \"unterminated",
+            "key: 'unterminated",
+            "key: \"\\q\"",
+            "key: {a: 1}",
+            "key: &anchor value",
+            "key: |+\n  text",
+            "- item",
+            "list:\n  - a: 1",
+            "a:\n\tb: 1",
+            "a:\n    b: 1\n  c: 2",
+            "no separator",
+            "\"\": 1",
+        ];
+        for text in invalid {
+            assert!(parse_str("env.yaml", text).is_err(), "{:?} must be rejected", text);
+        }
+    }
+}
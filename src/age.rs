@@ -0,0 +1,37 @@
+//! age encrypted env files
+//!
+//! Decrypted using `age` CLI (or program specified by `ENV_SMART_AGE`) with identity file pointed by `ENV_SMART_AGE_IDENTITY`.
+
+use std::path::{Path, PathBuf};
+
+use crate::{command, dotenv};
+
+const PROGRAM: &str = "ENV_SMART_AGE";
+const IDENTITY: &str = "ENV_SMART_AGE_IDENTITY";
+
+///Returns path of encrypted version of env file
+pub fn encrypted_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".age");
+    path.into()
+}
+
+///Decrypts and parses file, returning `None` if it doesn't exist
+pub fn parse_file(path: &Path, options: &dotenv::Options) -> Result<Option<Vec<dotenv::Entry>>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let name = path.display().to_string();
+    let identity = match std::env::var_os(IDENTITY) {
+        Some(identity) => identity,
+        None => return Err(format!("{name}: {IDENTITY} is not set")),
+    };
+    let program = std::env::var(PROGRAM).unwrap_or_else(|_| "age".to_owned());
+
+    let args = [std::ffi::OsStr::new("--decrypt"), "--identity".as_ref(), &identity, path.as_os_str()];
+    match command::run(&program, args, None) {
+        Ok(text) => dotenv::parse_str(&name, &text, options).map(Some).map_err(String::from),
+        Err(error) => Err(format!("{name}: Cannot decrypt: {error}")),
+    }
+}
@@ -0,0 +1,123 @@
+//! Audit manifest of embedded variables
+//!
+//! Enabled via `ENV_SMART_AUDIT` variable:
+//!
+//! - `1` - Write manifest into `OUT_DIR`
+//! - Otherwise value is treated as path to directory where to write manifest.
+//!
+//! Manifest is written as `env-smart-audit-<crate>-<kind>.json` containing JSON array of objects with fields `name`, `source`, `crate` and `kind`.
+//! Crate is name of binary for binary targets, while kind is crate type (`lib`, `bin` and so on), `test` for tests of library and integration tests, `bin-test` for tests of binary or `doc` for documentation and its tests.
+//! Both are part of name, as package's library, binaries and tests share crate name and `OUT_DIR`.
+
+use proc_macro::TokenStream;
+
+use core::fmt::Write;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::compile_error;
+use crate::vars::{Source, Vars};
+
+const AUDIT: &str = "ENV_SMART_AUDIT";
+
+static RECORDS: Mutex<BTreeSet<(String, String, Source)>> = Mutex::new(BTreeSet::new());
+
+fn json_str(out: &mut String, text: &str) {
+    out.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if ch.is_control() => {
+                let _ = write!(out, "\\u{:04x}", ch as u32);
+            },
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+//Kind of compiled target, which is known from arguments of compiler, as proc macro runs within its process
+fn target_kind(is_bin: bool) -> String {
+    let mut kind = None;
+    let mut args = std::env::args();
+    //Documentation is built and tested by rustdoc, which expands macros of library same as compiler
+    if args.next().map_or(false, |program| Path::new(&program).file_stem().map_or(false, |name| name == "rustdoc")) {
+        return "doc".to_owned();
+    }
+    while let Some(arg) = args.next() {
+        if arg == "--test" {
+            return if is_bin { "bin-test" } else { "test" }.to_owned();
+        } else if arg == "--crate-type" {
+            kind = args.next();
+        } else if let Some(value) = arg.strip_prefix("--crate-type=") {
+            kind = Some(value.to_owned());
+        }
+    }
+    kind.unwrap_or_else(|| "lib".to_owned())
+}
+
+fn manifest_dir(envs: &Vars) -> Result<Option<PathBuf>, TokenStream> {
+    match envs.setting(AUDIT) {
+        Some(value) if value.is_empty() || value == "0" => Ok(None),
+        Some(value) if crate::is_enabled(&value) => match std::env::var_os("OUT_DIR") {
+            Some(dir) => Ok(Some(dir.into())),
+            None => Err(compile_error(&format!("{AUDIT}: OUT_DIR is not available, specify directory explicitly"))),
+        },
+        Some(value) => Ok(Some(value.into())),
+        None => Ok(None),
+    }
+}
+
+///Records use of variable, re-writing manifest if audit is enabled.
+pub fn record(envs: &Vars, key: &str, source: Source) -> Result<(), TokenStream> {
+    let dir = match manifest_dir(envs)? {
+        Some(dir) => dir,
+        None => return Ok(()),
+    };
+
+    let bin_name = std::env::var("CARGO_BIN_NAME").ok();
+    let kind = target_kind(bin_name.is_some());
+    let crate_name = match bin_name {
+        Some(bin_name) => bin_name,
+        None => std::env::var("CARGO_CRATE_NAME").or_else(|_| std::env::var("CARGO_PKG_NAME")).unwrap_or_default(),
+    };
+    let mut records = match RECORDS.lock() {
+        Ok(records) => records,
+        Err(error) => error.into_inner(),
+    };
+
+    if !records.insert((crate_name.clone(), key.to_owned(), source)) {
+        return Ok(());
+    }
+
+    let mut manifest = String::new();
+    manifest.push('[');
+    for (idx, (_, name, source)) in records.iter().filter(|record| record.0 == crate_name).enumerate() {
+        if idx > 0 {
+            manifest.push(',');
+        }
+        manifest.push_str("\n  {\"name\":");
+        json_str(&mut manifest, name);
+        manifest.push_str(",\"source\":");
+        json_str(&mut manifest, &source.to_string());
+        manifest.push_str(",\"crate\":");
+        json_str(&mut manifest, &crate_name);
+        manifest.push_str(",\"kind\":");
+        json_str(&mut manifest, &kind);
+        manifest.push('}');
+    }
+    manifest.push_str("\n]\n");
+
+    let path = dir.join(format!("env-smart-audit-{crate_name}-{kind}.json"));
+    if let Err(error) = fs::create_dir_all(&dir).and_then(|_| fs::write(&path, manifest)) {
+        return Err(compile_error(&format!("{AUDIT}: Cannot write '{}': {error}", path.display())));
+    }
+
+    Ok(())
+}
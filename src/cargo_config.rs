@@ -0,0 +1,164 @@
+//! `[env]` table of cargo configuration files
+//!
+//! Files are discovered the same way as cargo does, walking up from working directory and then `$CARGO_HOME`.
+//! Compiler runs within directory of workspace (or crate outside of workspace), where cargo starts its search too.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::toml;
+
+#[derive(Debug)]
+///Variable defined in `[env]`
+pub struct Entry {
+    pub key: String,
+    pub value: String,
+    ///Whether it overrides variable already present in environment
+    pub force: bool,
+}
+
+fn cargo_home() -> Option<PathBuf> {
+    match std::env::var_os("CARGO_HOME") {
+        Some(home) => Some(PathBuf::from(home)),
+        None => std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(|home| Path::new(&home).join(".cargo")),
+    }
+}
+
+///Returns paths of configuration files that may exist, from most to least specific
+pub fn candidates() -> Vec<PathBuf> {
+    let dir = std::env::current_dir().ok().or_else(|| std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from));
+    candidates_of(dir.as_deref(), cargo_home())
+}
+
+fn candidates_of(dir: Option<&Path>, home: Option<PathBuf>) -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if let Some(dir) = dir {
+        dirs.extend(dir.ancestors().map(|dir| dir.join(".cargo")));
+    }
+    if let Some(home) = home {
+        if !dirs.contains(&home) {
+            dirs.push(home);
+        }
+    }
+
+    let mut result = Vec::new();
+    for dir in dirs {
+        //Legacy name without extension is used only if there is no `config.toml`
+        result.push(dir.join("config.toml"));
+        result.push(dir.join("config"));
+    }
+    result
+}
+
+fn parse(path: &Path, table: &toml::Table) -> Result<Vec<Entry>, String> {
+    let env = match table.get("env") {
+        Some(env) => match env.as_table() {
+            Some(env) => env,
+            None => return Err(format!("{}: 'env' must be table, got {}", path.display(), env.type_name())),
+        },
+        None => return Ok(Vec::new()),
+    };
+
+    let mut result = Vec::new();
+    for (key, value) in env.iter() {
+        let error = || format!("{}: env.{key} must be string or table with 'value', 'force' and 'relative'", path.display());
+        let (value, force, relative) = match value {
+            toml::Value::String(value) => (value.clone(), false, false),
+            toml::Value::Table(table) => {
+                let value = table.get("value").and_then(toml::Value::as_str).ok_or_else(error)?;
+                let flag = |name: &str| match table.get(name) {
+                    Some(flag) => flag.as_bool().ok_or_else(error),
+                    None => Ok(false),
+                };
+                (value.to_owned(), flag("force")?, flag("relative")?)
+            },
+            _ => return Err(error()),
+        };
+
+        let value = match relative {
+            //Relative to directory containing `.cargo`
+            true => match path.parent().and_then(Path::parent) {
+                Some(root) => root.join(value).display().to_string(),
+                None => value,
+            },
+            false => value,
+        };
+        result.push(Entry {
+            key: key.clone(),
+            value,
+            force,
+        });
+    }
+
+    Ok(result)
+}
+
+///Loads `[env]` entries of every configuration file, from most to least specific
+pub fn load() -> Result<Vec<Entry>, String> {
+    let mut result = Vec::new();
+    let mut candidates = candidates().into_iter();
+
+    while let Some(path) = candidates.next() {
+        let legacy = candidates.next();
+        let path = match (path.exists(), legacy) {
+            (true, _) => path,
+            (false, Some(legacy)) if legacy.exists() => legacy,
+            _ => continue,
+        };
+
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(error) => return Err(format!("{}: Cannot read: {error}", path.display())),
+        };
+        let table = toml::parse(&text).map_err(|error| format!("{}: {error}", path.display()))?;
+        result.extend(parse(&path, &table)?);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_walk_up_from_directory_before_cargo_home() {
+        let candidates = candidates_of(Some(Path::new("/work/crate")), Some(PathBuf::from("/home/.cargo")));
+        let expected: Vec<PathBuf> = ["/work/crate/.cargo", "/work/.cargo", "/.cargo", "/home/.cargo"].iter().flat_map(|dir| {
+            [Path::new(dir).join("config.toml"), Path::new(dir).join("config")]
+        }).collect();
+        assert_eq!(candidates, expected);
+
+        //Cargo home within walked directories is not searched twice
+        let candidates = candidates_of(Some(Path::new("/home")), Some(PathBuf::from("/home/.cargo")));
+        assert_eq!(candidates.len(), 4);
+    }
+
+    #[test]
+    fn should_search_working_directory() {
+        let dir = std::env::current_dir().unwrap().join(".cargo").join("config.toml");
+        assert_eq!(candidates().first(), Some(&dir));
+    }
+
+    #[test]
+    fn should_parse_env_table() {
+        let path = Path::new("/work/.cargo/config.toml");
+        let table = toml::parse("[env]\nPLAIN = \"value\"\nFORCED = { value = \"forced\", force = true }\nDATA = { value = \"data\", relative = true }\n").unwrap();
+        let mut entries = parse(path, &table).unwrap();
+        entries.sort_by(|left, right| left.key.cmp(&right.key));
+
+        let entries: Vec<(&str, &str, bool)> = entries.iter().map(|entry| (entry.key.as_str(), entry.value.as_str(), entry.force)).collect();
+        let data = Path::new("/work").join("data").display().to_string();
+        assert_eq!(entries, [("DATA", data.as_str(), false), ("FORCED", "forced", true), ("PLAIN", "value", false)]);
+
+        assert!(parse(path, &toml::parse("[other]\nKEY = \"value\"").unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn should_reject_invalid_env_table() {
+        let path = Path::new("config.toml");
+        for text in ["env = 1", "[env]\nKEY = 1", "[env]\nKEY = { force = true }", "[env]\nKEY = { value = \"value\", force = 1 }"] {
+            assert!(parse(path, &toml::parse(text).unwrap()).is_err(), "{:?} must be rejected", text);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! External commands
+
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+///Runs program, returning its stdout
+pub fn run<I: IntoIterator<Item = S>, S: AsRef<OsStr>>(program: &str, args: I, stdin: Option<&[u8]>) -> Result<String, String> {
+    let mut command = Command::new(program);
+    command.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
+    command.stdin(if stdin.is_some() { Stdio::piped() } else { Stdio::null() });
+
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Err(format!("Cannot run '{program}': It is not installed or not in PATH")),
+        Err(error) => return Err(format!("Cannot run '{program}': {error}")),
+    };
+
+    if let (Some(input), Some(mut pipe)) = (stdin, child.stdin.take()) {
+        if let Err(error) = pipe.write_all(input) {
+            return Err(format!("'{program}': Cannot write input: {error}"));
+        }
+    }
+
+    let output = match child.wait_with_output() {
+        Ok(output) => output,
+        Err(error) => return Err(format!("'{program}': {error}")),
+    };
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("'{program}' failed ({}): {}", output.status, stderr.trim()));
+    }
+
+    String::from_utf8(output.stdout).map_err(|_| format!("'{program}': Output is not valid unicode"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_report_missing_program() {
+        let error = run("env-smart-missing-program", ["--version"], None).unwrap_err();
+        assert_eq!(error, "Cannot run 'env-smart-missing-program': It is not installed or not in PATH");
+    }
+}
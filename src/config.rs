@@ -0,0 +1,324 @@
+//! Configuration file
+//!
+//! Loaded from `env-smart.toml` in directory where build is run (or path specified by `ENV_SMART_CONFIG`).
+//! Otherwise `[package.metadata.env-smart]` table of crate's `Cargo.toml` is used.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+
+use crate::dotenv::{self, Format};
+use crate::{env_toml, toml};
+
+const CONFIG: &str = "ENV_SMART_CONFIG";
+const CONFIG_FILE: &str = "env-smart.toml";
+
+#[derive(Debug)]
+pub struct Config {
+    ///Env files to load, in order. Later files override earlier ones.
+    pub files: Vec<PathBuf>,
+    ///Syntax of plain env files
+    pub format: Format,
+    ///Whether to interpolate variables within env file values
+    pub expand: bool,
+    ///Whether to replace leading `~` of env file values with home directory
+    pub expand_tilde: bool,
+    ///Options of `.env` syntax
+    pub dotenv: dotenv::Options,
+    ///Naming of variables within tables of TOML env files
+    pub toml_keys: env_toml::Keys,
+    ///Whether process environment overrides env files
+    pub process_override: Option<bool>,
+    pub case_insensitive: Option<bool>,
+    ///Whether to replace non-unicode characters instead of failing
+    pub lossy: Option<bool>,
+    ///Whether last definition of duplicate variable within file wins instead of failing
+    pub last_duplicate: Option<bool>,
+    pub deny_secrets: Option<bool>,
+    pub allowlist: Option<Vec<String>>,
+    ///Additional patterns of secret variable names
+    pub secret_patterns: Vec<String>,
+    ///Values used when variable is not defined anywhere else
+    pub defaults: Vec<(String, String)>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            files: vec![PathBuf::from(".env")],
+            format: Format::Dotenv,
+            expand: false,
+            expand_tilde: false,
+            dotenv: dotenv::Options::default(),
+            toml_keys: env_toml::Keys::Dotted,
+            process_override: None,
+            case_insensitive: None,
+            lossy: None,
+            last_duplicate: None,
+            deny_secrets: None,
+            allowlist: None,
+            secret_patterns: Vec::new(),
+            defaults: Vec::new(),
+        }
+    }
+}
+
+fn manifest_path() -> Option<PathBuf> {
+    std::env::var_os("CARGO_MANIFEST_DIR").map(|dir| Path::new(&dir).join("Cargo.toml"))
+}
+
+///Returns directory of workspace containing current crate, if crate is member of one
+pub fn workspace_root() -> Option<PathBuf> {
+    let dir = PathBuf::from(std::env::var_os("CARGO_MANIFEST_DIR")?);
+    //Workspace member's manifest may be its own root
+    dir.ancestors().find(|dir| {
+        fs::read_to_string(dir.join("Cargo.toml")).ok().and_then(|text| toml::parse(&text).ok()).map_or(false, |manifest| manifest.contains_key("workspace"))
+    }).map(Path::to_path_buf)
+}
+
+///Returns whether build runs within CI pipeline
+pub fn is_ci() -> bool {
+    std::env::var("CI").map_or(false, |value| crate::is_enabled(&value)) || ["GITHUB_ACTIONS", "GITLAB_CI"].iter().any(|name| std::env::var_os(name).is_some())
+}
+
+///Returns whether crate is compiled as test harness, which is the case for tests, benches and doctests
+///
+///Cargo doesn't tell proc macro about profile, but harness is requested by `--test` argument of compiler (or rustdoc).
+pub fn is_test() -> bool {
+    std::env::args().any(|arg| arg == "--test")
+}
+
+//Normalizes feature name as cargo does for `CARGO_FEATURE_*` variables
+fn feature_name(name: &str) -> String {
+    name.to_uppercase().replace('-', "_")
+}
+
+///Returns whether cargo feature of crate being compiled is enabled
+///
+///Features are passed to compiler as `--cfg feature="name"`, while build scripts get `CARGO_FEATURE_*` variables.
+pub fn has_feature(name: &str) -> bool {
+    let name = feature_name(name);
+    if std::env::var_os(format!("CARGO_FEATURE_{name}")).is_some() {
+        return true;
+    }
+
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        let cfg = match arg.strip_prefix("--cfg") {
+            Some("") => args.next(),
+            Some(cfg) => cfg.strip_prefix('=').map(str::to_owned),
+            None => continue,
+        };
+        let enabled = cfg.as_deref().and_then(|cfg| cfg.strip_prefix("feature=")).map(|feature| feature_name(feature.trim_matches('"')));
+        if enabled.as_deref() == Some(name.as_str()) {
+            return true;
+        }
+    }
+    false
+}
+
+///Returns triple of target being compiled for
+///
+///Proc macro runs within compiler, so it is `--target` of compiler or its host when not cross compiling.
+///`TARGET` variable, which cargo sets for build scripts, takes priority.
+pub fn target() -> Option<String> {
+    static TARGET: OnceLock<Option<String>> = OnceLock::new();
+
+    TARGET.get_or_init(|| {
+        if let Ok(target) = std::env::var("TARGET") {
+            return Some(target);
+        }
+
+        let mut args = std::env::args();
+        while let Some(arg) = args.next() {
+            let target = match arg.strip_prefix("--target") {
+                Some("") => args.next(),
+                Some(target) => target.strip_prefix('=').map(str::to_owned),
+                None => continue,
+            };
+            //Custom target is specified as path to its JSON specification
+            return target.map(|target| match target.strip_suffix(".json") {
+                Some(_) => Path::new(&target).file_stem().map_or_else(|| target.clone(), |stem| stem.to_string_lossy().into_owned()),
+                None => target,
+            });
+        }
+
+        //Other proc macro servers (e.g. rust-analyzer) are not asked, as they don't accept compiler's arguments
+        let compiler = std::env::current_exe().ok()?;
+        if !compiler.file_stem()?.to_string_lossy().starts_with("rustc") {
+            return None;
+        }
+        let output = Command::new(compiler).arg("-vV").output().ok()?;
+        String::from_utf8_lossy(&output.stdout).lines().find_map(|line| line.strip_prefix("host: ")).map(str::to_owned)
+    }).clone()
+}
+
+///Returns whether compilation target is Windows, assuming host if target is unknown
+pub fn is_windows_target() -> bool {
+    match target() {
+        Some(target) => target.contains("windows"),
+        None => cfg!(windows),
+    }
+}
+
+///Returns paths of files that may contain configuration
+pub fn candidates() -> Vec<PathBuf> {
+    let mut result = Vec::new();
+    match std::env::var_os(CONFIG) {
+        Some(path) => result.push(PathBuf::from(path)),
+        None => result.push(PathBuf::from(CONFIG_FILE)),
+    }
+    //Manifest always provides `[package.metadata.env]`
+    result.extend(manifest_path());
+    result
+}
+
+fn read_toml(path: &Path) -> Result<Option<toml::Table>, String> {
+    match fs::read_to_string(path) {
+        Ok(text) => match toml::parse(&text) {
+            Ok(table) => Ok(Some(table)),
+            Err(error) => Err(format!("{}: {error}", path.display())),
+        },
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(format!("{}: Cannot read: {error}", path.display())),
+    }
+}
+
+fn string_list(name: &str, value: &toml::Value) -> Result<Vec<String>, String> {
+    let mut result = Vec::new();
+    match value.as_array() {
+        Some(array) => for item in array {
+            match item.as_str() {
+                Some(item) => result.push(item.to_owned()),
+                None => return Err(format!("'{name}' must contain only strings, got {}", item.type_name())),
+            }
+        },
+        None => return Err(format!("'{name}' must be array of strings, got {}", value.type_name())),
+    }
+    Ok(result)
+}
+
+fn boolean(name: &str, value: &toml::Value) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| format!("'{name}' must be boolean, got {}", value.type_name()))
+}
+
+fn choice(name: &str, value: &toml::Value, variants: [&str; 2]) -> Result<bool, String> {
+    match value.as_str() {
+        Some(value) if value == variants[0] => Ok(false),
+        Some(value) if value == variants[1] => Ok(true),
+        _ => Err(format!("'{name}' must be either '{}' or '{}'", variants[0], variants[1])),
+    }
+}
+
+fn scalar_table(name: &str, value: &toml::Value) -> Result<Vec<(String, String)>, String> {
+    let mut result = Vec::new();
+    match value.as_table() {
+        Some(table) => for (key, value) in table.iter() {
+            match value.to_scalar_string() {
+                Some(value) => result.push((key.clone(), value)),
+                None => return Err(format!("'{name}.{key}' must be scalar value, got {}", value.type_name())),
+            }
+        },
+        None => return Err(format!("'{name}' must be table, got {}", value.type_name())),
+    }
+    Ok(result)
+}
+
+///Loads variables declared in `[package.metadata.env]` of crate's `Cargo.toml`
+pub fn manifest_env() -> Result<Vec<(String, String)>, String> {
+    let path = match manifest_path() {
+        Some(path) => path,
+        None => return Ok(Vec::new()),
+    };
+
+    match read_toml(&path)? {
+        Some(manifest) => match toml::get(&manifest, &["package", "metadata", "env"]) {
+            Some(env) => scalar_table("package.metadata.env", env).map_err(|error| format!("{}: {error}", path.display())),
+            None => Ok(Vec::new()),
+        },
+        None => Ok(Vec::new()),
+    }
+}
+
+impl Config {
+    fn from_table(table: &toml::Table) -> Result<Self, String> {
+        let mut result = Self::default();
+
+        for (name, value) in table.iter() {
+            match name.as_str() {
+                "files" => result.files = string_list(name, value)?.into_iter().map(PathBuf::from).collect(),
+                "format" => result.format = match value.as_str() {
+                    Some("dotenv") => Format::Dotenv,
+                    Some("compose") => Format::Compose,
+                    Some("systemd") => Format::Systemd,
+                    Some("json") => Format::Json,
+                    #[cfg(feature = "yaml")]
+                    Some("yaml") => Format::Yaml,
+                    _ => return Err(format!("'{name}' must be one of 'dotenv', 'compose', 'systemd', 'json' or 'yaml' (with `yaml` feature)")),
+                },
+                "expand" => result.expand = boolean(name, value)?,
+                "expand_tilde" => result.expand_tilde = boolean(name, value)?,
+                "trim_whitespace" => result.dotenv.trim = boolean(name, value)?,
+                "line_continuation" => result.dotenv.continuation = boolean(name, value)?,
+                "key_names" => result.dotenv.key_names = match value.as_str() {
+                    Some("any") => dotenv::KeyNames::Any,
+                    Some("portable") => dotenv::KeyNames::Portable,
+                    Some("uppercase") => dotenv::KeyNames::Uppercase,
+                    _ => return Err(format!("'{name}' must be one of 'any', 'portable' or 'uppercase'")),
+                },
+                "toml_keys" => result.toml_keys = match choice(name, value, ["dotted", "uppercase"])? {
+                    false => env_toml::Keys::Dotted,
+                    true => env_toml::Keys::Uppercase,
+                },
+                "precedence" => result.process_override = Some(choice(name, value, ["dotenv", "process"])?),
+                "case_insensitive" => result.case_insensitive = Some(boolean(name, value)?),
+                "non_utf8" => result.lossy = Some(choice(name, value, ["strict", "lossy"])?),
+                "duplicates" => result.last_duplicate = Some(choice(name, value, ["error", "last"])?),
+                "deny_secrets" => result.deny_secrets = Some(boolean(name, value)?),
+                "allowlist" => result.allowlist = Some(string_list(name, value)?),
+                "keys" => result.dotenv.keys = string_list(name, value)?,
+                "secret_patterns" => result.secret_patterns = string_list(name, value)?.into_iter().map(|pattern| pattern.to_ascii_uppercase()).collect(),
+                "defaults" => result.defaults = scalar_table(name, value)?,
+                _ => return Err(format!("Unknown option '{name}'")),
+            }
+        }
+        //Settings can be defined by env files
+        if !result.dotenv.keys.is_empty() {
+            result.dotenv.keys.push("ENV_SMART_*".to_owned());
+        }
+
+        Ok(result)
+    }
+
+    ///Loads configuration, returning default one if there is no configuration file
+    pub fn load() -> Result<Self, String> {
+        let path = match std::env::var_os(CONFIG) {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                return match read_toml(&path)? {
+                    Some(table) => Self::from_table(&table).map_err(|error| format!("{}: {error}", path.display())),
+                    None => Err(format!("{CONFIG}: '{}' does not exist", path.display())),
+                };
+            },
+            None => PathBuf::from(CONFIG_FILE),
+        };
+
+        if let Some(table) = read_toml(&path)? {
+            return Self::from_table(&table).map_err(|error| format!("{}: {error}", path.display()));
+        }
+
+        if let Some(path) = manifest_path() {
+            if let Some(manifest) = read_toml(&path)? {
+                if let Some(table) = toml::get(&manifest, &["package", "metadata", "env-smart"]) {
+                    return match table.as_table() {
+                        Some(table) => Self::from_table(table).map_err(|error| format!("{}: package.metadata.env-smart: {error}", path.display())),
+                        None => Err(format!("{}: package.metadata.env-smart must be table", path.display())),
+                    };
+                }
+            }
+        }
+
+        Ok(Self::default())
+    }
+}
@@ -0,0 +1,82 @@
+//! Directory of files, where file name is variable name and its content is value
+//!
+//! Matches layout of mounted Kubernetes ConfigMap or Secret, which also contains hidden `..data` entries that are skipped.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::dotenv;
+
+///Returns whether configured path refers to directory
+pub fn is_dir(path: &Path) -> bool {
+    path.is_dir() || path.as_os_str().to_string_lossy().ends_with(['/', '\\'])
+}
+
+///Reads files of directory in order of their names, returning `None` if directory doesn't exist
+///
+///Read files are added to `included`.
+pub fn parse_dir(path: &Path, included: &mut Vec<PathBuf>) -> Result<Option<Vec<dotenv::Entry>>, String> {
+    let name = path.display().to_string();
+    let dir = match fs::read_dir(path) {
+        Ok(dir) => dir,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("{name}: Cannot open directory: {error}")),
+    };
+
+    let mut files = Vec::new();
+    for file in dir {
+        let file = file.map_err(|error| format!("{name}: Cannot list directory: {error}"))?;
+        let key = match file.file_name().into_string() {
+            Ok(key) if !key.starts_with('.') => key,
+            Ok(_) => continue,
+            Err(key) => return Err(format!("{name}: File name '{}' is not valid unicode", key.to_string_lossy())),
+        };
+        //Entries are symlinks within mounted volume
+        if fs::metadata(file.path()).map_or(false, |metadata| metadata.is_file()) {
+            files.push((key, file.path()));
+        }
+    }
+    files.sort();
+
+    let mut entries = Vec::with_capacity(files.len());
+    for (key, file) in files {
+        let value = dotenv::read_text(&file).map_err(|error| format!("{}: Cannot read: {error}", file.display()))?;
+        //Files written by editors end with line break, unlike ones created from literal
+        let value = value.strip_suffix('\n').map(|value| value.strip_suffix('\r').unwrap_or(value)).unwrap_or(&value);
+        entries.push(dotenv::Entry::new(key, value.to_owned(), &Arc::from(file.display().to_string()), 1));
+        included.push(file);
+    }
+    Ok(Some(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_read_files_of_directory_as_variables() {
+        let dir = std::env::temp_dir().join(format!("env-smart-configmap-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        fs::write(dir.join("B_KEY"), "line\n").unwrap();
+        fs::write(dir.join("A_KEY"), "first\r\nsecond").unwrap();
+        fs::write(dir.join("..data"), "hidden").unwrap();
+        fs::write(dir.join("nested").join("KEY"), "nested").unwrap();
+
+        let mut included = Vec::new();
+        let entries = parse_dir(&dir, &mut included);
+        let missing = parse_dir(&dir.join("missing"), &mut Vec::new());
+        let _ = fs::remove_dir_all(&dir);
+
+        let entries: Vec<(String, String)> = entries.unwrap().unwrap().into_iter().map(|entry| (entry.key, entry.value)).collect();
+        assert_eq!(entries, [("A_KEY".to_owned(), "first\r\nsecond".to_owned()), ("B_KEY".to_owned(), "line".to_owned())]);
+        assert_eq!(included, [dir.join("A_KEY"), dir.join("B_KEY")]);
+        assert!(missing.unwrap().is_none());
+    }
+
+    #[test]
+    fn should_treat_trailing_separator_as_directory() {
+        assert!(is_dir(Path::new("config/")));
+        assert!(!is_dir(Path::new("missing-config-file")));
+    }
+}
@@ -0,0 +1,56 @@
+//! Non-fatal diagnostics
+//!
+//! Stable proc macros cannot emit warnings, so these are printed to stderr, which is shown by cargo.
+//! With `nightly` feature these are emitted as compiler diagnostics at macro's call site instead.
+
+use core::fmt;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy)]
+pub enum Level {
+    Note,
+    Warning,
+}
+
+impl fmt::Display for Level {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Note => fmt.write_str("note"),
+            Self::Warning => fmt.write_str("warning"),
+        }
+    }
+}
+
+static EMITTED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+///Emits diagnostic message
+#[cfg(not(feature = "nightly"))]
+pub fn emit(level: Level, message: &str) {
+    eprintln!("{level}: env-smart: {message}");
+}
+
+///Emits diagnostic message
+#[cfg(feature = "nightly")]
+pub fn emit(level: Level, message: &str) {
+    let level = match level {
+        Level::Note => proc_macro::Level::Note,
+        Level::Warning => proc_macro::Level::Warning,
+    };
+    proc_macro::Diagnostic::spanned(proc_macro::Span::call_site(), level, format!("env-smart: {message}")).emit();
+}
+
+///Emits diagnostic message only once per process
+pub fn emit_once(level: Level, message: String) {
+    let mut emitted = match EMITTED.lock() {
+        Ok(emitted) => emitted,
+        Err(error) => error.into_inner(),
+    };
+
+    let emitted = emitted.get_or_insert_with(HashSet::new);
+    if !emitted.contains(&message) {
+        emit(level, &message);
+        emitted.insert(message);
+    }
+}
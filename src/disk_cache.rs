@@ -0,0 +1,310 @@
+//! On-disk cache of variables loaded from env files
+//!
+//! Crate is compiled by many compiler processes (library, binaries, tests, every rebuild), which would otherwise parse the same env files again.
+//! Variables are stored within `env-smart` directory of target directory, in file named after digest of settings and list of env files.
+//! Cache is used only while digest of every watched file matches, and it is disabled by `ENV_SMART_DISK_CACHE=0`.
+//! Cache file is memory-mapped when read, as it is replaced by rename and never modified in place.
+//!
+//! Values, which depend on anything but content of files (e.g. decrypted or downloaded), are never stored.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::{sha256, EnvFiles};
+use std::sync::Arc;
+
+use crate::vars::{Origin, Source, Var, Vars};
+
+const DISK_CACHE: &str = "ENV_SMART_DISK_CACHE";
+const HEADER: &str = concat!("env-smart-cache ", env!("CARGO_PKG_VERSION"));
+
+fn dir() -> Option<PathBuf> {
+    if std::env::var(DISK_CACHE).map_or(false, |value| !crate::is_enabled(&value)) {
+        return None;
+    }
+    let target = match std::env::var_os("CARGO_TARGET_DIR") {
+        Some(target) => PathBuf::from(target),
+        None => crate::config::workspace_root().or_else(|| std::env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from))?.join("target"),
+    };
+    Some(target.join("env-smart"))
+}
+
+fn file_digest(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|content| sha256::hex_digest(&content))
+}
+
+//Digest of file's content, or of names and contents of files within directory
+fn digest(path: &Path) -> String {
+    if let Some(digest) = file_digest(path) {
+        return digest;
+    }
+    match fs::read_dir(path) {
+        Ok(dir) => {
+            let mut entries: Vec<String> = dir.filter_map(|entry| entry.ok()).map(|entry| {
+                let content = file_digest(&entry.path()).unwrap_or_else(|| "-".to_owned());
+                format!("{}\t{content}", entry.file_name().to_string_lossy())
+            }).collect();
+            entries.sort_unstable();
+            sha256::hex_digest(entries.join("\n").as_bytes())
+        },
+        Err(_) => "-".to_owned(),
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('\t', "\\t").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => result.push('\t'),
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some(ch) => result.push(ch),
+            None => (),
+        }
+    }
+    result
+}
+
+///Returns key of cache for settings affecting loading and files watched before loading
+pub fn key(flags: &[bool], watched: &[PathBuf]) -> String {
+    let mut input = String::from(HEADER);
+    for var in ["CARGO_MANIFEST_DIR", "CARGO_PKG_NAME"] {
+        input.push('\n');
+        input.push_str(&std::env::var(var).unwrap_or_default());
+    }
+    //Relative paths are resolved against working directory
+    if let Ok(dir) = std::env::current_dir() {
+        input.push('\n');
+        input.push_str(&dir.to_string_lossy());
+    }
+    input.push('\n');
+    input.extend(flags.iter().map(|flag| if *flag { '1' } else { '0' }));
+    for path in watched {
+        input.push('\n');
+        input.push_str(&path.to_string_lossy());
+    }
+    sha256::hex_digest(input.as_bytes())
+}
+
+///Loads cached variables of env files along with env files and whether they exist
+///
+///Cached list of watched files, which includes files discovered while loading, replaces `watched`.
+pub fn load(key: &str, case_insensitive: bool, lossy: bool, watched: &mut Vec<PathBuf>) -> Option<(Vars, EnvFiles)> {
+    let file = fs::File::open(dir()?.join(key)).ok()?;
+    //SAFETY: Cache files are written to temporary file and renamed, so mapped file is never modified
+    let text = unsafe { memmap2::Mmap::map(&file) }.ok()?;
+    let (vars, files, cached_watched) = parse(core::str::from_utf8(&text).ok()?, case_insensitive, lossy)?;
+    *watched = cached_watched;
+    Some((vars, files))
+}
+
+//Parses cache, returning `None` if it is malformed or any watched file changed
+fn parse(text: &str, case_insensitive: bool, lossy: bool) -> Option<(Vars, EnvFiles, Vec<PathBuf>)> {
+    let mut lines = text.lines();
+    if lines.next()? != HEADER {
+        return None;
+    }
+
+    let mut vars = Vars::new(case_insensitive, lossy);
+    let mut files = Vec::new();
+    let mut cached_watched = Vec::new();
+    //Removed variables are restored first, as variables can be defined again after removal
+    let mut defined = Vec::new();
+    for line in lines {
+        let mut fields = line.split('\t').map(unescape);
+        match fields.next()?.as_str() {
+            "watch" => {
+                let (hash, path) = (fields.next()?, PathBuf::from(fields.next()?));
+                if digest(&path) != hash {
+                    return None;
+                }
+                cached_watched.push(path);
+            },
+            "file" => {
+                let exists = fields.next()? == "1";
+                files.push((PathBuf::from(fields.next()?), exists));
+            },
+            "unset" => vars.unset(&fields.next()?),
+            "var" | "item" => defined.push(line),
+            _ => return None,
+        }
+    }
+    let mut interned: Option<Arc<str>> = None;
+    for line in defined {
+        let mut fields = line.split('\t').map(unescape);
+        match fields.next()?.as_str() {
+            "var" => {
+                let (key, value) = (fields.next()?, fields.next()?);
+                let mut var = Var::new(value, Source::DotEnv);
+                if let (Some(file), Some(line)) = (fields.next(), fields.next()) {
+                    if interned.as_deref() != Some(file.as_str()) {
+                        interned = Some(Arc::from(file));
+                    }
+                    var.origin = interned.clone().map(|file| Origin {
+                        file,
+                        line: line.parse().unwrap_or_default(),
+                    });
+                }
+                var.value_file = fields.next().map(PathBuf::from);
+                vars.insert(&key, var);
+            },
+            _ => {
+                let (key, value) = (fields.next()?, fields.next()?);
+                vars.push(&key, value, false);
+            },
+        }
+    }
+
+    Some((vars, files, cached_watched))
+}
+
+///Stores variables of env files, ignoring failures as cache is only optimization
+pub fn store(key: &str, vars: &Vars, files: &[(PathBuf, bool)], watched: &[PathBuf]) {
+    let dir = match dir() {
+        Some(dir) => dir,
+        None => return,
+    };
+    let text = format(vars, files, watched);
+
+    //Other compiler processes can read cache at the same time, so it is replaced at once
+    let temp = dir.join(format!("{key}.{}", std::process::id()));
+    if fs::create_dir_all(&dir).is_ok() && fs::write(&temp, text).is_ok() && fs::rename(&temp, dir.join(key)).is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+}
+
+fn format(vars: &Vars, files: &[(PathBuf, bool)], watched: &[PathBuf]) -> String {
+    let mut text = String::from(HEADER);
+    for path in watched {
+        text.push_str(&format!("\nwatch\t{}\t{}", digest(path), escape(&path.to_string_lossy())));
+    }
+    for (path, exists) in files {
+        text.push_str(&format!("\nfile\t{}\t{}", if *exists { 1 } else { 0 }, escape(&path.to_string_lossy())));
+    }
+    let (defined, arrays, unset) = vars.file_layer();
+    for key in unset {
+        text.push_str(&format!("\nunset\t{}", escape(key)));
+    }
+    for (key, var) in defined {
+        text.push_str(&format!("\nvar\t{}\t{}", escape(key), escape(&var.value)));
+        if let Some(origin) = var.origin.as_ref() {
+            text.push_str(&format!("\t{}\t{}", escape(&origin.file), origin.line));
+        }
+        if let Some(value_file) = var.value_file.as_ref() {
+            text.push_str(&format!("\t{}", escape(&value_file.to_string_lossy())));
+        }
+    }
+    for (key, array) in arrays {
+        for item in array {
+            text.push_str(&format!("\nitem\t{}\t{}", escape(key), escape(item)));
+        }
+    }
+    text.push('\n');
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("env-smart-cache-{name}-{}", std::process::id()))
+    }
+
+    #[test]
+    fn should_restore_stored_variables() {
+        let watched = temp_path("watched.env");
+        fs::write(&watched, "KEY=value").unwrap();
+
+        let mut vars = Vars::new(false, false);
+        let mut var = Var::new("multi\tline\nvalue\\".to_owned(), Source::DotEnv);
+        var.origin = Some(Origin {
+            file: Arc::from(".env"),
+            line: 3,
+        });
+        var.value_file = Some(PathBuf::from("cert.pem"));
+        vars.insert("KEY", var);
+        vars.insert("PLAIN", Var::new(String::new(), Source::DotEnv));
+        vars.push("LIST", "first".to_owned(), false);
+        vars.push("LIST", "second".to_owned(), false);
+        vars.unset("REMOVED");
+        let files = vec![(PathBuf::from(".env"), true), (PathBuf::from(".env.local"), false)];
+
+        let text = format(&vars, &files, core::slice::from_ref(&watched));
+        assert!(text.starts_with(HEADER));
+        for kind in ["watch", "file", "unset", "var", "item"] {
+            assert!(text.lines().any(|line| line.starts_with(&format!("{kind}\t"))), "{} line must be stored", kind);
+        }
+
+        let (restored, restored_files, restored_watched) = parse(&text, false, false).unwrap();
+        let _ = fs::remove_file(&watched);
+        assert_eq!(restored_files, files);
+        assert_eq!(restored_watched, [watched]);
+
+        let var = restored.get_file("KEY").unwrap();
+        assert_eq!(&*var.value, "multi\tline\nvalue\\");
+        let origin = var.origin.as_ref().unwrap();
+        assert_eq!((&*origin.file, origin.line), (".env", 3));
+        assert_eq!(var.value_file.as_deref(), Some(Path::new("cert.pem")));
+        assert_eq!(&*restored.get_file("PLAIN").unwrap().value, "");
+        assert_eq!(restored.get_array("LIST").unwrap(), ["first", "second"]);
+        assert_eq!(restored.file_layer().2.collect::<Vec<_>>(), ["REMOVED"]);
+    }
+
+    #[test]
+    fn should_invalidate_cache_when_watched_file_changes() {
+        let watched = temp_path("changed.env");
+        let missing = temp_path("missing.env");
+        fs::write(&watched, "KEY=old").unwrap();
+        let text = format(&Vars::new(false, false), &[], &[watched.clone(), missing.clone()]);
+        assert!(parse(&text, false, false).is_some());
+
+        fs::write(&watched, "KEY=new").unwrap();
+        assert!(parse(&text, false, false).is_none());
+        fs::write(&watched, "KEY=old").unwrap();
+        assert!(parse(&text, false, false).is_some());
+
+        //Creating file, which didn't exist, invalidates cache too
+        fs::write(&missing, "").unwrap();
+        let result = parse(&text, false, false);
+        let _ = fs::remove_file(&watched);
+        let _ = fs::remove_file(&missing);
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn should_invalidate_cache_when_file_within_watched_directory_changes() {
+        let dir = temp_path("configmap");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("KEY"), "old").unwrap();
+        let text = format(&Vars::new(false, false), &[], core::slice::from_ref(&dir));
+        assert!(parse(&text, false, false).is_some());
+
+        fs::write(dir.join("KEY"), "new").unwrap();
+        let changed = parse(&text, false, false);
+        fs::write(dir.join("KEY"), "old").unwrap();
+        fs::write(dir.join("OTHER"), "old").unwrap();
+        let added = parse(&text, false, false);
+        let _ = fs::remove_dir_all(&dir);
+        assert!(changed.is_none());
+        assert!(added.is_none());
+    }
+
+    #[test]
+    fn should_reject_malformed_cache() {
+        assert!(parse("", false, false).is_none());
+        assert!(parse("env-smart-cache 0.0.0\nunset\tKEY", false, false).is_none());
+        for line in ["unknown\tKEY", "var\tKEY", "item\tKEY", "file\t1", "watch\t-"] {
+            assert!(parse(&format!("{HEADER}\n{line}"), false, false).is_none(), "{:?} must be rejected", line);
+        }
+    }
+}
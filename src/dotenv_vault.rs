@@ -0,0 +1,141 @@
+//! [dotenv-vault](https://www.dotenv.org/docs/security/env-vault) files
+//!
+//! `.env.vault` is decrypted using key from `DOTENV_KEY`, which has format `dotenv://:key_<hex>@dotenv.org/vault/.env.vault?environment=<name>`.
+//! Multiple keys can be separated by comma, in which case first successful one is used.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+
+use crate::dotenv;
+
+const DOTENV_KEY: &str = "DOTENV_KEY";
+const KEY_LEN: usize = 64;
+const NONCE_LEN: usize = 12;
+
+///Returns path of vault for env file
+pub fn vault_path(path: &Path) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".vault");
+    path.into()
+}
+
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    fn value(byt: u8) -> Option<u32> {
+        match byt {
+            b'A'..=b'Z' => Some((byt - b'A') as u32),
+            b'a'..=b'z' => Some((byt - b'a' + 26) as u32),
+            b'0'..=b'9' => Some((byt - b'0' + 52) as u32),
+            b'+' | b'-' => Some(62),
+            b'/' | b'_' => Some(63),
+            _ => None,
+        }
+    }
+
+    let text = text.trim().trim_end_matches('=');
+    let mut result = Vec::with_capacity(text.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for byt in text.bytes() {
+        acc = (acc << 6) | value(byt)?;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Some(result)
+}
+
+fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    //Odd length fails on last pair
+    (0..text.len()).step_by(2).map(|idx| u8::from_str_radix(text.get(idx..idx + 2)?, 16).ok()).collect()
+}
+
+//Parses DOTENV_KEY into key and environment name
+fn parse_key(key: &str) -> Result<(Vec<u8>, String), String> {
+    let rest = match key.trim().strip_prefix("dotenv://") {
+        Some(rest) => rest,
+        None => return Err("Expected 'dotenv://' scheme".to_owned()),
+    };
+    let (credentials, rest) = match rest.split_once('@') {
+        Some(parts) => parts,
+        None => return Err("Missing key".to_owned()),
+    };
+    let password = credentials.rsplit(':').next().unwrap_or_default();
+    let key = match password.len().checked_sub(KEY_LEN).and_then(|start| hex_decode(&password[start..])) {
+        Some(key) => key,
+        None => return Err("Invalid key".to_owned()),
+    };
+
+    let environment = rest.split_once('?').and_then(|(_, query)| {
+        query.split('&').find_map(|param| param.strip_prefix("environment="))
+    });
+    match environment {
+        Some(environment) if !environment.is_empty() => Ok((key, environment.to_owned())),
+        _ => Err("Missing environment".to_owned()),
+    }
+}
+
+fn decrypt(key: &[u8], data: &str) -> Result<String, String> {
+    let data = match base64_decode(data) {
+        Some(data) if data.len() > NONCE_LEN => data,
+        _ => return Err("Invalid ciphertext".to_owned()),
+    };
+    let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+
+    let cipher = match Aes256Gcm::new_from_slice(key) {
+        Ok(cipher) => cipher,
+        Err(_) => return Err("Invalid key length".to_owned()),
+    };
+    match cipher.decrypt(Nonce::from_slice(nonce), ciphertext) {
+        Ok(text) => String::from_utf8(text).map_err(|_| "Decrypted content is not valid unicode".to_owned()),
+        Err(_) => Err("Decryption failed".to_owned()),
+    }
+}
+
+///Decrypts and parses vault, returning `None` if it doesn't exist or `DOTENV_KEY` is not set
+pub fn parse_file(path: &Path, options: &dotenv::Options) -> Result<Option<Vec<dotenv::Entry>>, String> {
+    let keys = match std::env::var(DOTENV_KEY) {
+        Ok(keys) => keys,
+        Err(_) => return Ok(None),
+    };
+
+    let name = path.display().to_string();
+    let vault = match fs::read_to_string(path) {
+        Ok(text) => dotenv::parse_str(&name, &text, &dotenv::Options::default())?,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(error) => return Err(format!("{name}: Cannot open: {error}")),
+    };
+
+    let mut last_error = String::new();
+    for key in keys.split(',') {
+        let (key, environment) = match parse_key(key) {
+            Ok(key) => key,
+            Err(error) => {
+                last_error = format!("{DOTENV_KEY}: {error}");
+                continue;
+            }
+        };
+
+        let entry_name = format!("DOTENV_VAULT_{}", environment.to_ascii_uppercase());
+        let entry = match vault.iter().find(|entry| entry.key == entry_name) {
+            Some(entry) => entry,
+            None => {
+                last_error = format!("Missing environment {entry_name}");
+                continue;
+            }
+        };
+
+        match decrypt(&key, &entry.value) {
+            Ok(text) => return dotenv::parse_str(&format!("{name}#{environment}"), &text, options).map(Some).map_err(String::from),
+            Err(error) => last_error = format!("{entry_name}: {error}"),
+        }
+    }
+
+    Err(format!("{name}: {last_error}"))
+}
@@ -0,0 +1,110 @@
+//! TOML env files, such as `env.toml`
+//!
+//! Tables are flattened into variable names, while arrays of scalars define arrays.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::{dotenv, toml};
+
+pub const FILE: &str = "env.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///Naming of variables within tables
+pub enum Keys {
+    ///`url` within `[database]` defines `database.url`
+    Dotted,
+    ///`url` within `[database]` defines `DATABASE_URL`
+    Uppercase,
+}
+
+impl Keys {
+    fn join(&self, prefix: &str, key: &str) -> String {
+        match (self, prefix.is_empty()) {
+            (Self::Dotted, true) => key.to_owned(),
+            (Self::Dotted, false) => format!("{prefix}.{key}"),
+            (Self::Uppercase, true) => key.to_uppercase(),
+            (Self::Uppercase, false) => format!("{prefix}_{}", key.to_uppercase()),
+        }
+    }
+}
+
+//Finds line, where key of table is defined, so that origin of variable can be reported
+//
+//Keys defined through dotted keys or inline tables are attributed to their table header.
+fn line_of(text: &str, table: &[&str], key: &str) -> usize {
+    let mut current = Vec::new();
+    let mut header_line = 1;
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.trim_start_matches('[');
+            let header = header.split(']').next().unwrap_or_default();
+            current = header.split('.').map(|part| part.trim().trim_matches(['"', '\''])).collect();
+            if current == table {
+                header_line = idx + 1;
+            }
+            continue;
+        }
+        if current == table {
+            let name = line.split('=').next().unwrap_or_default().trim().trim_matches(['"', '\'']);
+            if name == key {
+                return idx + 1;
+            }
+        }
+    }
+    header_line
+}
+
+fn flatten(name: &Arc<str>, text: &str, keys: Keys, path: &mut Vec<String>, prefix: &str, table: &toml::Table, entries: &mut Vec<dotenv::Entry>) -> Result<(), String> {
+    for (key, value) in table.iter() {
+        let var = keys.join(prefix, key);
+        let line = || {
+            let table: Vec<&str> = path.iter().map(String::as_str).collect();
+            line_of(text, &table, key)
+        };
+        match value {
+            toml::Value::Table(table) => {
+                path.push(key.clone());
+                flatten(name, text, keys, path, &var, table, entries)?;
+                path.pop();
+            },
+            toml::Value::Array(array) => for item in array {
+                match item.to_scalar_string() {
+                    Some(value) => {
+                        let mut entry = dotenv::Entry::new(var.clone(), value, name, line());
+                        entry.op = dotenv::Op::Push;
+                        entries.push(entry);
+                    },
+                    None => return Err(format!("{name}: {var}: Array can only contain scalar values, got {}", item.type_name())),
+                }
+            },
+            value => match value.to_scalar_string() {
+                Some(value) => entries.push(dotenv::Entry::new(var, value, name, line())),
+                None => return Err(format!("{name}: {var}: Unsupported {} value", value.type_name())),
+            },
+        }
+    }
+    Ok(())
+}
+
+///Parses content of file
+pub fn parse_str(name: &str, text: &str, keys: Keys) -> Result<Vec<dotenv::Entry>, String> {
+    let table = toml::parse(text).map_err(|error| format!("{name}: {error}"))?;
+    let mut entries = Vec::new();
+    flatten(&Arc::from(name), text, keys, &mut Vec::new(), "", &table, &mut entries)?;
+    Ok(entries)
+}
+
+///Parses file, returning `None` if it doesn't exist
+pub fn parse_file(path: &Path, keys: Keys) -> Result<Option<Vec<dotenv::Entry>>, String> {
+    let name = path.display().to_string();
+    match dotenv::read_text(path) {
+        Ok(text) => parse_str(&name, &text, keys).map(Some),
+        Err(error) => match error.kind() {
+            std::io::ErrorKind::NotFound => Ok(None),
+            std::io::ErrorKind::InvalidData => Err(format!("{name}: Read fail: {error}")),
+            _ => Err(format!("{name}: Cannot open: {error}")),
+        },
+    }
+}
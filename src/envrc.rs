@@ -0,0 +1,95 @@
+//! direnv `.envrc` file parser
+//!
+//! Only plain `export KEY=VALUE` lines are supported, as anything else requires shell to evaluate.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::diagnostic::{self, Level};
+use crate::dotenv::{self, Entry};
+
+fn unquote(value: &str) -> Option<&str> {
+    for quote in ['"', '\''] {
+        if let Some(value) = value.strip_prefix(quote) {
+            return value.strip_suffix(quote).filter(|value| !value.contains(quote));
+        }
+    }
+
+    //Unquoted value is only plain if shell has nothing to expand or split
+    if value.contains(|ch: char| ch.is_whitespace() || "$`\\\"';&|<>(){}*?".contains(ch)) {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+fn is_valid_key(key: &str) -> bool {
+    !key.is_empty() && !key.starts_with(|ch: char| ch.is_ascii_digit()) && key.chars().all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+///Parses content of file, ignoring unsupported lines with warning
+pub fn parse_str(name: &str, text: &str) -> Vec<Entry> {
+    let file: Arc<str> = Arc::from(name);
+    let mut entries = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let entry = line.strip_prefix("export ").and_then(|definition| definition.trim_start().split_once('=')).and_then(|(key, value)| {
+            if is_valid_key(key) {
+                unquote(value).map(|value| Entry::new(key.to_owned(), value.to_owned(), &file, idx + 1))
+            } else {
+                None
+            }
+        });
+
+        match entry {
+            Some(entry) => entries.push(entry),
+            None => diagnostic::emit_once(Level::Warning, format!("{name}:{}: Unsupported shell construct is ignored", idx + 1)),
+        }
+    }
+
+    entries
+}
+
+///Parses file, returning `None` if it doesn't exist
+pub fn parse_file(path: &Path) -> Result<Option<Vec<Entry>>, String> {
+    let name = path.display().to_string();
+    match dotenv::read_text(path) {
+        Ok(text) => Ok(Some(parse_str(&name, &text))),
+        Err(error) => match error.kind() {
+            io::ErrorKind::NotFound => Ok(None),
+            io::ErrorKind::InvalidData => Err(format!("{name}: Read fail: {error}")),
+            _ => Err(format!("{name}: Cannot open: {error}")),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Vec<(String, String, usize)> {
+        parse_str(".envrc", text).into_iter().map(|entry| (entry.key, entry.value, entry.line)).collect()
+    }
+
+    #[test]
+    fn should_parse_plain_exports() {
+        let entries = parse("# comment\n\nexport PLAIN=value\nexport  DOUBLE=\"two words\"\nexport SINGLE='$HOME'\nexport EMPTY=\n");
+        assert_eq!(entries, [
+            ("PLAIN".to_owned(), "value".to_owned(), 3),
+            ("DOUBLE".to_owned(), "two words".to_owned(), 4),
+            ("SINGLE".to_owned(), "$HOME".to_owned(), 5),
+            ("EMPTY".to_owned(), String::new(), 6),
+        ]);
+    }
+
+    #[test]
+    fn should_ignore_shell_constructs() {
+        let text = "use nix\nKEY=value\nexport PATH=$PATH:bin\nexport CMD=`date`\nexport 1KEY=value\nexport KEY-NAME=value\nexport QUOTED=\"a\"b\"\nexport UNCLOSED=\"value\nexport LIST=a b";
+        assert!(parse(text).is_empty());
+    }
+}
@@ -0,0 +1,144 @@
+//! Variable interpolation within env file values, following dotenv-expand rules
+//!
+//! Supports `$KEY`, `${KEY}`, `${KEY:-default}`, `${KEY-default}`, `${KEY:+alternative}`, `${KEY+alternative}` and `\$` escape.
+//! Default and alternative can contain references too, including nested `${...}`. Undefined variables expand to empty string.
+
+fn is_name_start(ch: char) -> bool {
+    ch.is_ascii_alphabetic() || ch == '_'
+}
+
+fn is_name_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == '_'
+}
+
+//Returns index of `}` closing expression, skipping nested braces
+fn closing_brace(braced: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    for (idx, ch) in braced.char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' if depth == 0 => return Some(idx),
+            '}' => depth -= 1,
+            _ => (),
+        }
+    }
+    None
+}
+
+//Evaluates content of `${...}`
+fn expression(expr: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    //Same as dotenv-expand, first operator within expression is used
+    let operator = [":+", "+", ":-", "-"].iter().filter_map(|op| expr.find(op).map(|idx| (idx, *op))).min_by_key(|(idx, _)| *idx);
+
+    match operator {
+        Some((idx, op)) => {
+            let key = &expr[..idx];
+            let operand = &expr[idx + op.len()..];
+            let value = lookup(key).filter(|value| !value.is_empty());
+            if op.ends_with('+') {
+                match value {
+                    Some(_) => expand(operand, lookup),
+                    None => String::new(),
+                }
+            } else {
+                match value {
+                    Some(value) => value,
+                    None => expand(operand, lookup),
+                }
+            }
+        },
+        None => lookup(expr).unwrap_or_default(),
+    }
+}
+
+///Expands references to variables within `value`
+pub fn expand(value: &str, lookup: &impl Fn(&str) -> Option<String>) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+
+    while let Some(idx) = rest.find(['$', '\\']) {
+        result.push_str(&rest[..idx]);
+        let tail = &rest[idx..];
+
+        if let Some(tail) = tail.strip_prefix("\\$") {
+            result.push('$');
+            rest = tail;
+        } else if let Some(tail) = tail.strip_prefix('\\') {
+            result.push('\\');
+            rest = tail;
+        } else if let Some(braced) = tail.strip_prefix("${") {
+            match closing_brace(braced) {
+                Some(end) if end > 0 => {
+                    result.push_str(&expression(&braced[..end], lookup));
+                    rest = &braced[end + 1..];
+                },
+                _ => {
+                    result.push('$');
+                    rest = &tail[1..];
+                },
+            }
+        } else {
+            let name = &tail[1..];
+            let len = match name.chars().next() {
+                Some(ch) if is_name_start(ch) => name.find(|ch| !is_name_char(ch)).unwrap_or(name.len()),
+                _ => 0,
+            };
+            if len == 0 {
+                result.push('$');
+            } else {
+                result.push_str(&lookup(&name[..len]).unwrap_or_default());
+            }
+            rest = &name[len..];
+        }
+    }
+
+    result.push_str(rest);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand_with(value: &str, vars: &[(&str, &str)]) -> String {
+        expand(value, &|key: &str| vars.iter().find(|(name, _)| *name == key).map(|(_, value)| (*value).to_owned()))
+    }
+
+    #[test]
+    fn should_expand_references() {
+        let vars = [("A", "a"), ("A_B", "ab")];
+        assert_eq!(expand_with("$A/${A}/$A_B/${A}_B", &vars), "a/a/ab/a_B");
+        assert_eq!(expand_with("\\$A \\\\ $ $1 ${}", &vars), "$A \\\\ $ $1 ${}");
+    }
+
+    #[test]
+    fn should_expand_undefined_references_to_empty_string() {
+        assert_eq!(expand_with("[$MISSING][${MISSING}]", &[]), "[][]");
+    }
+
+    #[test]
+    fn should_use_default_of_undefined_or_empty_variable() {
+        let vars = [("SET", "set"), ("EMPTY", "")];
+        assert_eq!(expand_with("${SET:-b}|${EMPTY:-b}|${MISSING:-b}", &vars), "set|b|b");
+        //Same as dotenv-expand, empty value is treated as undefined with and without colon
+        assert_eq!(expand_with("${SET-b}|${EMPTY-b}|${MISSING-b}", &vars), "set|b|b");
+        assert_eq!(expand_with("${SET:-}|${MISSING:-}", &vars), "set|");
+    }
+
+    #[test]
+    fn should_use_alternative_of_defined_variable() {
+        let vars = [("SET", "set"), ("EMPTY", "")];
+        assert_eq!(expand_with("${SET:+b}|${EMPTY:+b}|${MISSING:+b}", &vars), "b||");
+        assert_eq!(expand_with("${SET+b}|${MISSING+b}", &vars), "b|");
+    }
+
+    #[test]
+    fn should_expand_nested_references() {
+        let vars = [("HOST", "localhost"), ("PORT", "80")];
+        assert_eq!(expand_with("${URL:-http://${HOST}:${PORT:-8080}}", &vars), "http://localhost:80");
+        assert_eq!(expand_with("${URL:-${MISSING:-$HOST}}/path", &vars), "localhost/path");
+        assert_eq!(expand_with("${HOST:+${PORT}}", &vars), "80");
+        //Unclosed expression is kept as is
+        assert_eq!(expand_with("${URL:-${HOST}", &vars), "${URL:-localhost");
+    }
+}
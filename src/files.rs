@@ -0,0 +1,223 @@
+//! Env files to load
+
+use std::path::{Path, PathBuf};
+
+use crate::config::{self, Config};
+use crate::{configmap, dotenv, env_toml, envrc};
+
+const ENVRC: &str = ".envrc";
+
+#[derive(Debug, Clone, Copy)]
+pub enum Kind {
+    ///Plain `.env` file
+    Plain(dotenv::Format),
+    ///Optional plain file layered over configured one
+    Overlay(dotenv::Format),
+    ///direnv `.envrc` file
+    Envrc,
+    ///Directory of files named after variables, such as mounted ConfigMap
+    Dir,
+    ///TOML file, which tables are flattened
+    Toml(env_toml::Keys),
+    #[cfg(feature = "age")]
+    ///age encrypted `.env` file
+    Age,
+    #[cfg(feature = "dotenv-vault")]
+    ///dotenv-vault file
+    Vault,
+    #[cfg(feature = "sops")]
+    ///SOPS encrypted file
+    Sops(crate::sops::Format),
+    #[cfg(feature = "remote")]
+    ///Env file fetched from URL, which is stored as path
+    Remote,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvFile {
+    pub path: PathBuf,
+    pub kind: Kind,
+}
+
+impl EnvFile {
+    #[inline]
+    ///Returns whether file is plain text, rather than encrypted version of other file
+    pub fn is_plain(&self) -> bool {
+        matches!(self.kind, Kind::Plain(_) | Kind::Envrc | Kind::Dir)
+    }
+
+    ///Reads file, returning `None` if it doesn't exist
+    ///
+    ///Files included by it are added to `included`.
+    pub fn read(&self, options: &dotenv::Options, included: &mut Vec<PathBuf>) -> Result<Option<Vec<dotenv::Entry>>, String> {
+        match self.kind {
+            Kind::Plain(format) | Kind::Overlay(format) => dotenv::parse_file(&self.path, format, options, included).map_err(String::from),
+            Kind::Envrc => envrc::parse_file(&self.path),
+            Kind::Dir => configmap::parse_dir(&self.path, included),
+            Kind::Toml(keys) => env_toml::parse_file(&self.path, keys),
+            #[cfg(feature = "age")]
+            Kind::Age => crate::age::parse_file(&self.path, options),
+            #[cfg(feature = "dotenv-vault")]
+            Kind::Vault => crate::dotenv_vault::parse_file(&self.path, options),
+            #[cfg(feature = "sops")]
+            Kind::Sops(format) => crate::sops::parse_file(&self.path, format, options),
+            #[cfg(feature = "remote")]
+            Kind::Remote => crate::remote::parse(&self.path.to_string_lossy(), options),
+        }
+    }
+}
+
+///Returns path of file referenced by `@file:path` value, relative to directory of env file
+pub fn value_file(env_file: &Path, value: &str) -> Option<PathBuf> {
+    let path = value.strip_prefix("@file:")?;
+    Some(env_file.parent().unwrap_or_else(|| Path::new("")).join(path))
+}
+
+//Relative path is loaded from workspace root first and then overlaid by crate's directory
+fn layers(path: &Path) -> Vec<PathBuf> {
+    let crate_dir = match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(dir) if path.is_relative() => PathBuf::from(dir),
+        _ => return vec![path.to_path_buf()],
+    };
+
+    let mut result = Vec::with_capacity(2);
+    if let Some(root) = config::workspace_root() {
+        if root != crate_dir {
+            result.push(root.join(path));
+        }
+    }
+    result.push(crate_dir.join(path));
+    result
+}
+
+//Syntax of file is determined by its extension, falling back to configured one
+fn format_of(path: &Path, config: &Config) -> dotenv::Format {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("json") => dotenv::Format::Json,
+        #[cfg(feature = "yaml")]
+        Some("yaml" | "yml") => dotenv::Format::Yaml,
+        _ => config.format,
+    }
+}
+
+//Appends `.suffix` to file name
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(".");
+    path.push(suffix);
+    PathBuf::from(path)
+}
+
+///Lists files to load in order. Later files override earlier ones.
+pub fn list(config: &Config) -> Vec<EnvFile> {
+    let mut result = Vec::new();
+    if !crate::DOTENV_SOURCE {
+        return result;
+    }
+    let ci = config::is_ci();
+    let test = config::is_test();
+    let target = config::target();
+
+    //`env.toml` provides base configuration, which env files override
+    for path in layers(Path::new(env_toml::FILE)) {
+        result.push(EnvFile {
+            path,
+            kind: Kind::Toml(config.toml_keys),
+        });
+    }
+
+    let paths = config.files.iter().flat_map(|path| layers(path));
+    for path in paths {
+        let path = &path;
+        if path.file_name().map_or(false, |name| name == ENVRC) {
+            result.push(EnvFile {
+                path: path.clone(),
+                kind: Kind::Envrc,
+            });
+            continue;
+        }
+        if configmap::is_dir(path) {
+            result.push(EnvFile {
+                path: path.clone(),
+                kind: Kind::Dir,
+            });
+            continue;
+        }
+        if path.extension().map_or(false, |extension| extension == "toml") {
+            //`env.toml` is already loaded
+            if !result.iter().any(|file| file.path == *path) {
+                result.push(EnvFile {
+                    path: path.clone(),
+                    kind: Kind::Toml(config.toml_keys),
+                });
+            }
+            continue;
+        }
+
+        let format = format_of(path, config);
+        result.push(EnvFile {
+            path: path.clone(),
+            kind: Kind::Plain(format),
+        });
+        //Files in other formats are only loaded next to ones in configured format
+        if format == config.format {
+            result.push(EnvFile {
+                path: with_suffix(path, "json"),
+                kind: Kind::Overlay(dotenv::Format::Json),
+            });
+            #[cfg(feature = "yaml")]
+            for extension in ["yaml", "yml"] {
+                result.push(EnvFile {
+                    path: with_suffix(path, extension),
+                    kind: Kind::Overlay(dotenv::Format::Yaml),
+                });
+            }
+        }
+        #[cfg(feature = "age")]
+        result.push(EnvFile {
+            path: crate::age::encrypted_path(path),
+            kind: Kind::Age,
+        });
+        #[cfg(feature = "dotenv-vault")]
+        result.push(EnvFile {
+            path: crate::dotenv_vault::vault_path(path),
+            kind: Kind::Vault,
+        });
+        #[cfg(feature = "sops")]
+        for (path, format) in crate::sops::encrypted_paths(path) {
+            result.push(EnvFile {
+                path,
+                kind: Kind::Sops(format),
+            });
+        }
+        if test {
+            result.push(EnvFile {
+                path: with_suffix(path, "test"),
+                kind: Kind::Overlay(config.format),
+            });
+        }
+        if ci {
+            result.push(EnvFile {
+                path: with_suffix(path, "ci"),
+                kind: Kind::Overlay(config.format),
+            });
+        }
+        if let Some(target) = target.as_ref() {
+            result.push(EnvFile {
+                path: with_suffix(path, target),
+                kind: Kind::Overlay(config.format),
+            });
+        }
+    }
+
+    //Remote file is distributed by build farm to override files of repository
+    #[cfg(feature = "remote")]
+    if let Some(url) = crate::remote::url() {
+        result.push(EnvFile {
+            path: PathBuf::from(url),
+            kind: Kind::Remote,
+        });
+    }
+
+    result
+}
@@ -1,23 +1,53 @@
 use core::fmt;
-use std::collections::HashMap;
+use core::mem;
+use std::borrow::Cow;
+
+use crate::modifier;
+use crate::vars::{NonUnicode, Source, Var, Vars};
 
 #[derive(Debug)]
 pub enum Part<'a, 'b> {
     Plain(&'a str),
-    Argument(&'b str),
+    ///Variable, which is borrowed from resolver unless its value is produced by lookup or modifiers
+    Argument(&'a str, Cow<'b, Var>),
+}
+
+impl Part<'_, '_> {
+    #[inline]
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Plain(plain) => plain,
+            Self::Argument(_, var) => &var.value,
+        }
+    }
 }
 
 pub struct Format<'a, 'b> {
     input: &'a str,
-    vars: &'b HashMap<String, String>,
+    vars: &'b Vars,
+    ///Values bound by macro call, which take precedence over `vars`
+    overrides: &'b [(String, String)],
     consumed: usize,
 }
 
 #[derive(Debug)]
 pub enum FormatError<'a> {
     MissingValue(&'a str),
+    NonUnicode(&'a str),
+    InvalidName(&'a str, usize),
     MissingClosingBracket(usize),
     BracketEscapeInvalid(usize),
+    Modifier(&'a str, String),
+}
+
+impl FormatError<'_> {
+    ///Returns byte offset within input, which error refers to
+    pub fn offset(&self) -> Option<usize> {
+        match self {
+            Self::InvalidName(_, idx) | Self::MissingClosingBracket(idx) | Self::BracketEscapeInvalid(idx) => Some(*idx),
+            Self::MissingValue(_) | Self::NonUnicode(_) | Self::Modifier(..) => None,
+        }
+    }
 }
 
 impl fmt::Display for FormatError<'_> {
@@ -25,22 +55,70 @@ impl fmt::Display for FormatError<'_> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Self::MissingValue(name) => fmt.write_fmt(format_args!("env:{name}: missing value")),
+            Self::NonUnicode(name) => fmt.write_fmt(format_args!("env:{name}: value is not valid unicode")),
+            Self::InvalidName(name, idx) => fmt.write_fmt(format_args!("Invalid variable name '{}' at position {idx}", name.escape_debug())),
             Self::MissingClosingBracket(idx) => fmt.write_fmt(format_args!("Missing bracket at position {idx}")),
             Self::BracketEscapeInvalid(idx) => fmt.write_fmt(format_args!("Unsupported bracket escape at position {idx}")),
+            Self::Modifier(name, error) => fmt.write_fmt(format_args!("env:{name}: {error}")),
         }
     }
 }
 
+#[derive(Debug)]
+pub enum Segment<'a> {
+    Plain(&'a str),
+    Name(&'a str),
+}
+
+///Splits input into plain text and variable names, without resolving them
+pub fn segments(mut input: &str) -> Vec<Segment<'_>> {
+    let mut result = Vec::new();
+    while let Some(start) = input.find('{') {
+        let end = match input[start..].find('}') {
+            Some(end) => start + end,
+            None => break,
+        };
+        if start > 0 {
+            result.push(Segment::Plain(&input[..start]));
+        }
+        result.push(Segment::Name(&input[start + 1..end]));
+        input = &input[end + 1..];
+    }
+    if !input.is_empty() {
+        result.push(Segment::Plain(input));
+    }
+    result
+}
+
+fn is_invalid_name_char(ch: char) -> bool {
+    matches!(ch, '=' | '"' | '\'' | '{') || ch.is_control()
+}
+
+///Returns name of first `{NAME}` within value, which would be placeholder if it was input
+pub fn placeholder(value: &str) -> Option<&str> {
+    segments(value).into_iter().find_map(|segment| match segment {
+        Segment::Name(name) if !name.is_empty() && !name.contains(|ch: char| is_invalid_name_char(ch) || ch.is_whitespace()) => Some(name),
+        _ => None,
+    })
+}
+
 impl<'a, 'b> Format<'a, 'b> {
-    pub fn new(input: &'a str, vars: &'b HashMap<String, String>) -> Self {
+    pub fn new(input: &'a str, vars: &'b Vars) -> Self {
         Self {
             input,
             vars,
+            overrides: &[],
             consumed: 0
         }
     }
 
-    pub fn next(&mut self) -> Option<Result<Part<'a, 'b>, FormatError>> {
+    #[inline]
+    pub fn with_overrides(mut self, overrides: &'b [(String, String)]) -> Self {
+        self.overrides = overrides;
+        self
+    }
+
+    pub fn next(&mut self) -> Option<Result<Part<'a, 'b>, FormatError<'a>>> {
         const ARG_START: char = '{';
         const ARG_END: char = '}';
 
@@ -50,22 +128,42 @@ impl<'a, 'b> Format<'a, 'b> {
 
         if self.input.as_bytes()[0] == ARG_START as u8 {
             //double brackets not allowed
-            if self.input.as_bytes()[1] == ARG_START as u8 {
+            if self.input.as_bytes().get(1) == Some(&(ARG_START as u8)) {
                 return Some(Err(FormatError::BracketEscapeInvalid(self.consumed + 1)))
             };
 
             if let Some(idx) = self.input.find(ARG_END) {
-                let key = &self.input[1..idx];
-                if let Some(value) = self.vars.get(key) {
+                let placeholder = &self.input[1..idx];
+                let (key, modifiers) = modifier::split(placeholder);
+                if let Some(invalid) = key.find(is_invalid_name_char) {
+                    return Some(Err(FormatError::InvalidName(key, self.consumed + 1 + invalid)));
+                }
+
+                let value = match self.overrides.iter().find(|(name, _)| name == key) {
+                    Some((_, value)) => Ok(Some(Cow::Owned(Var::new(value.clone(), Source::Call)))),
+                    None => self.vars.get(key),
+                };
+                let value = match value {
+                    Ok(value) => value,
+                    Err(NonUnicode) => return Some(Err(FormatError::NonUnicode(key))),
+                };
+                if let Some(mut value) = value {
+                    if let Some(modifiers) = modifiers {
+                        let value = value.to_mut();
+                        value.value = match modifier::apply(modifiers, mem::take(&mut value.value).into_string()) {
+                            Ok(value) => value.into_boxed_str(),
+                            Err(error) => return Some(Err(FormatError::Modifier(key, error))),
+                        };
+                    }
                     let new_input = &self.input[idx+1..];
 
-                    if let Some(true) = new_input.as_bytes().get(0).map(|byt| *byt == ARG_END as u8) {
-                        return Some(Err(FormatError::BracketEscapeInvalid(self.consumed + key.len() + 1)))
+                    if let Some(true) = new_input.as_bytes().first().map(|byt| *byt == ARG_END as u8) {
+                        return Some(Err(FormatError::BracketEscapeInvalid(self.consumed + placeholder.len() + 1)))
                     }
 
                     self.input = new_input;
-                    self.consumed = self.consumed.saturating_add(key.len() + 2);
-                    Some(Ok(Part::Argument(value.as_str())))
+                    self.consumed = self.consumed.saturating_add(placeholder.len() + 2);
+                    Some(Ok(Part::Argument(key, value)))
                 } else {
                     Some(Err(FormatError::MissingValue(key)))
                 }
@@ -85,3 +183,55 @@ impl<'a, 'b> Format<'a, 'b> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn format(input: &str, vars: &Vars) -> Result<String, String> {
+        let mut formatter = Format::new(input, vars);
+        let mut result = String::new();
+        while let Some(part) = formatter.next() {
+            match part {
+                Ok(part) => result.push_str(part.as_str()),
+                Err(error) => return Err(error.to_string()),
+            }
+        }
+        Ok(result)
+    }
+
+    #[test]
+    fn should_substitute_variables() {
+        let mut vars = Vars::new(false, false);
+        vars.insert("KEY", Var::new("value".to_owned(), Source::DotEnv));
+        assert_eq!(format("a{KEY}b{KEY}", &vars).unwrap(), "avaluebvalue");
+        assert_eq!(format("plain", &vars).unwrap(), "plain");
+        assert_eq!(format("{MISSING}", &vars).unwrap_err(), "env:MISSING: missing value");
+    }
+
+    #[test]
+    fn should_report_unclosed_bracket_without_panic() {
+        let vars = Vars::new(false, false);
+        for (input, offset) in [("{", 0), ("a{", 1), ("ab{KEY", 2)] {
+            let mut formatter = Format::new(input, &vars);
+            let error = loop {
+                match formatter.next() {
+                    Some(Ok(_)) => continue,
+                    Some(Err(error)) => break error,
+                    None => panic!("{:?} must fail", input),
+                }
+            };
+            assert!(matches!(error, FormatError::MissingClosingBracket(position) if position == offset), "{}: {}", input, error);
+            assert_eq!(error.offset(), Some(offset));
+        }
+    }
+
+    #[test]
+    fn should_reject_bracket_escapes() {
+        let mut vars = Vars::new(false, false);
+        vars.insert("KEY", Var::new("value".to_owned(), Source::DotEnv));
+        assert_eq!(format("{{KEY}", &vars).unwrap_err(), "Unsupported bracket escape at position 1");
+        assert_eq!(format("{KEY}}", &vars).unwrap_err(), "Unsupported bracket escape at position 4");
+        assert_eq!(format("{KEY=1}", &vars).unwrap_err(), "Invalid variable name 'KEY=1' at position 4");
+    }
+}
@@ -0,0 +1,161 @@
+//! String literal handling
+
+use core::ops::Range;
+
+const QUOTE: char = '"';
+//Bytes of byte string above ASCII are kept as characters of private use plane, as input is expanded as text
+const HIGH_BYTES: u32 = 0x10FF00;
+
+fn unescape(text: &str, bytes: bool) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('0') => result.push('\0'),
+            Some('\\') => result.push('\\'),
+            Some('\'') => result.push('\''),
+            Some('"') => result.push('"'),
+            Some('x') => {
+                let code: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&code, 16) {
+                    Ok(code) if code <= 0x7f => result.push(code as char),
+                    Ok(code) if bytes => result.extend(char::from_u32(HIGH_BYTES + u32::from(code))),
+                    _ => return Err(format!("Invalid escape '\\x{code}'")),
+                }
+            },
+            Some('u') => {
+                if chars.next() != Some('{') {
+                    return Err("Invalid unicode escape".to_owned());
+                }
+                let code: String = chars.by_ref().take_while(|ch| *ch != '}').filter(|ch| *ch != '_').collect();
+                match u32::from_str_radix(&code, 16).ok().and_then(char::from_u32) {
+                    Some(ch) => result.push(ch),
+                    None => return Err(format!("Invalid unicode escape '\\u{{{code}}}'")),
+                }
+            },
+            //Line continuation skips new line and leading whitespace
+            Some('\n') | Some('\r') => while let Some(' ' | '\t' | '\n' | '\r') = chars.peek() {
+                chars.next();
+            },
+            Some(ch) => return Err(format!("Unknown escape '\\{ch}'")),
+            None => return Err("Unterminated escape".to_owned()),
+        }
+    }
+
+    Ok(result)
+}
+
+//Returns content of raw string literal `r#"..."#`, which has no escapes
+fn raw_str(token: &str) -> Option<&str> {
+    let text = token.strip_prefix('r')?;
+    let hashes = text.len() - text.trim_start_matches('#').len();
+    let text = text[hashes..].strip_prefix(QUOTE)?;
+    let text = text.strip_suffix(&token[1..1 + hashes])?;
+    text.strip_suffix(QUOTE)
+}
+
+fn parse(token: &str, bytes: bool) -> Option<Result<String, String>> {
+    if let Some(text) = raw_str(token) {
+        return Some(Ok(text.to_owned()));
+    }
+    let text = token.strip_prefix(QUOTE)?.strip_suffix(QUOTE)?;
+    Some(unescape(text, bytes))
+}
+
+///Parses byte string literal `b"..."` from its token representation, returning `None` if it is not byte string literal
+///
+///Bytes are taken as text, as byte string can only contain ASCII characters and escapes.
+///Result of expanding it must be converted with `into_bytes`, which restores `\x80`-`\xFF` escapes within text of literal.
+pub fn parse_byte_str(token: &str) -> Option<Result<String, String>> {
+    parse(token.strip_prefix('b')?, true)
+}
+
+///Parses string literal from its token representation, returning `None` if it is not string literal
+pub fn parse_str(token: &str) -> Option<Result<String, String>> {
+    parse(token, false)
+}
+
+///Converts text expanded from byte string literal to bytes
+///
+///Only `literal` ranges of text come from literal, while the rest is substituted values, which are kept as they are.
+pub fn into_bytes(text: String, literal: &[Range<usize>]) -> Vec<u8> {
+    let high_byte = |(idx, ch): (usize, char)| (ch as u32).checked_sub(HIGH_BYTES)
+                                                          .filter(|byt| (0x80..=0xFF).contains(byt) && literal.iter().any(|range| range.contains(&idx)))
+                                                          .map(|byt| byt as u8);
+    if !text.char_indices().any(|ch| high_byte(ch).is_some()) {
+        return text.into_bytes();
+    }
+
+    let mut result = Vec::with_capacity(text.len());
+    for (idx, ch) in text.char_indices() {
+        match high_byte((idx, ch)) {
+            Some(byt) => result.push(byt),
+            None => result.extend_from_slice(ch.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+    result
+}
+
+///Returns range of character at `offset` of parsed `text` within `token` of literal
+///
+///Returns `None` unless literal is written without escapes, as offsets would not match then.
+#[cfg_attr(not(feature = "nightly"), allow(dead_code))]
+pub fn source_range(token: &str, text: &str, offset: usize) -> Option<Range<usize>> {
+    let start = token.find(QUOTE)? + 1;
+    let end = start + text.len();
+    if token.get(start..end)? != text || token[end..].trim_end_matches('#') != "\"" {
+        return None;
+    }
+    let len = text.get(offset..)?.chars().next().map_or(0, char::len_utf8);
+    Some(start + offset..start + offset + len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_parse_high_bytes_of_byte_string() {
+        let text = parse_byte_str(r#"b"\xFF{KEY}\x80\x41""#).unwrap().unwrap();
+        let literal = 0..text.len();
+        assert_eq!(into_bytes(text, core::slice::from_ref(&literal)), b"\xFF{KEY}\x80A");
+        assert_eq!(into_bytes("value".to_owned(), &[]), b"value");
+    }
+
+    #[test]
+    fn should_keep_substituted_text_of_byte_string() {
+        let text = parse_byte_str(r#"b"\xFF{KEY}""#).unwrap().unwrap();
+        let literal = 0..text.len();
+        //Value of variable can contain the same private use characters, which stand for high bytes of literal
+        let value = format!("{text}{}", char::from_u32(HIGH_BYTES + 0xFF).unwrap());
+        let mut expected = b"\xFF{KEY}".to_vec();
+        expected.extend_from_slice("\u{10FFFF}".as_bytes());
+        assert_eq!(into_bytes(value, core::slice::from_ref(&literal)), expected);
+    }
+
+    #[test]
+    fn should_reject_high_bytes_of_string() {
+        assert!(parse_str(r#""\xFF""#).unwrap().is_err());
+        assert_eq!(parse_str(r#""\x41""#).unwrap().unwrap(), "A");
+    }
+
+    #[test]
+    fn should_map_offset_into_literal_source() {
+        assert_eq!(source_range(r#""a{B""#, "a{B", 1), Some(2..3));
+        assert_eq!(source_range(r#"b"a{B""#, "a{B", 1), Some(3..4));
+        assert_eq!(source_range(r##"r#"a{é"#"##, "a{é", 2), Some(5..7));
+        assert_eq!(source_range(r#""a{""#, "a{", 2), Some(3..3));
+        assert_eq!(source_range(r#""{\\""#, "{\\", 0), None);
+        assert_eq!(source_range(r#""\n{""#, "\n{", 1), None);
+        assert_eq!(source_range(r#""a{""#, "a{", 3), None);
+    }
+}
@@ -0,0 +1,204 @@
+//! Modifiers of placeholder's value: `{KEY:modifier}`
+//!
+//! Modifiers can be chained as `{KEY:first:second}`, being applied from left to right.
+
+///Splits placeholder into variable name and its modifiers
+pub fn split(placeholder: &str) -> (&str, Option<&str>) {
+    match placeholder.split_once(':') {
+        Some((name, modifiers)) => (name, Some(modifiers)),
+        None => (placeholder, None),
+    }
+}
+
+//Home directory of user running build
+fn home_dir() -> Option<String> {
+    let vars: &[&str] = if cfg!(windows) { &["USERPROFILE", "HOME"] } else { &["HOME", "USERPROFILE"] };
+    vars.iter().find_map(|name| std::env::var(name).ok().filter(|home| !home.is_empty())).or_else(|| {
+        let drive = std::env::var("HOMEDRIVE").ok()?;
+        let path = std::env::var("HOMEPATH").ok()?;
+        Some(drive + &path)
+    })
+}
+
+///Replaces leading `~` with home directory, leaving value as is if home is unknown
+pub fn expand_tilde(value: &str) -> String {
+    let rest = match value.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with(['/', '\\']) => rest,
+        _ => return value.to_owned(),
+    };
+    match home_dir() {
+        Some(home) => home + rest,
+        None => value.to_owned(),
+    }
+}
+
+///Converts path separators to ones of compilation target
+pub fn native_path(value: &str) -> String {
+    match crate::config::is_windows_target() {
+        true => value.replace('/', "\\"),
+        false => value.replace('\\', "/"),
+    }
+}
+
+#[cfg(feature = "unicode")]
+//Normalizes unicode form, so that composed and decomposed characters compare equal
+fn normalize(name: &str, value: &str) -> Result<String, String> {
+    use unicode_normalization::UnicodeNormalization;
+
+    match name {
+        "nfkc" => Ok(value.nfkc().collect()),
+        _ => Ok(value.nfc().collect()),
+    }
+}
+
+#[cfg(not(feature = "unicode"))]
+fn normalize(name: &str, _: &str) -> Result<String, String> {
+    Err(format!("{name}: Modifier requires `unicode` feature"))
+}
+
+//Parses arguments of modifier up to closing parenthesis, returning rest of input
+//
+//Argument is either quoted with `'` or `"`, or taken as is without surrounding whitespace.
+fn arguments(mut input: &str) -> Result<(Vec<String>, &str), String> {
+    let mut args = Vec::new();
+    loop {
+        input = input.trim_start();
+        let arg = match input.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let quoted = &input[1..];
+                let end = quoted.find(quote).ok_or_else(|| format!("Missing closing {quote} within modifier arguments"))?;
+                input = quoted[end + 1..].trim_start();
+                quoted[..end].to_owned()
+            },
+            Some(_) => {
+                let end = input.find([',', ')']).unwrap_or(input.len());
+                let arg = input[..end].trim().to_owned();
+                input = &input[end..];
+                arg
+            },
+            None => return Err("Missing closing ')' of modifier arguments".to_owned()),
+        };
+        args.push(arg);
+        match input.chars().next() {
+            Some(',') => input = &input[1..],
+            Some(')') => return Ok((args, &input[1..])),
+            _ => return Err("Expected ',' or ')' within modifier arguments".to_owned()),
+        }
+    }
+}
+
+//Returns element of delimited value, where negative index counts from end
+fn split_index(value: &str, separator: &str, index: &str) -> Result<String, String> {
+    if separator.is_empty() {
+        return Err("split: Separator cannot be empty".to_owned());
+    }
+    let index: isize = index.parse().map_err(|_| format!("split: Expected index, got '{index}'"))?;
+    let items: Vec<&str> = value.split(separator).collect();
+    let position = match index {
+        index if index < 0 => items.len().checked_sub(index.unsigned_abs()),
+        index => Some(index as usize),
+    };
+    match position.and_then(|position| items.get(position)) {
+        Some(item) => Ok((*item).to_owned()),
+        None => Err(format!("split: Index {index} is out of range for {} elements", items.len())),
+    }
+}
+
+///Applies chain of modifiers to value
+pub fn apply(mut modifiers: &str, mut value: String) -> Result<String, String> {
+    loop {
+        let end = modifiers.find([':', '(']).unwrap_or(modifiers.len());
+        let name = modifiers[..end].trim();
+        modifiers = &modifiers[end..];
+        let args = match modifiers.strip_prefix('(') {
+            Some(rest) => {
+                let (args, rest) = arguments(rest)?;
+                modifiers = rest.trim_start();
+                args
+            },
+            None => Vec::new(),
+        };
+
+        value = match (name, args.as_slice()) {
+            ("expand_tilde", []) => expand_tilde(&value),
+            ("path", []) => native_path(&value),
+            ("split", [separator, index]) => split_index(&value, separator, index)?,
+            ("nfc" | "nfkc", []) => normalize(name, &value)?,
+            ("expand_tilde" | "path" | "nfc" | "nfkc", _) => return Err(format!("{name}: Modifier does not accept arguments")),
+            ("split", _) => return Err("split: Expected separator and index: split(',', 0)".to_owned()),
+            _ => return Err(format!("Unknown modifier '{name}', expected one of expand_tilde, nfc, nfkc, path, split")),
+        };
+
+        match modifiers.strip_prefix(':') {
+            Some(rest) => modifiers = rest,
+            None if modifiers.is_empty() => return Ok(value),
+            None => return Err(format!("{name}: Expected ':' after modifier")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_split_name_and_modifiers() {
+        assert_eq!(split("KEY"), ("KEY", None));
+        assert_eq!(split("KEY:path:split(',', 0)"), ("KEY", Some("path:split(',', 0)")));
+    }
+
+    #[test]
+    fn should_select_element_by_index() {
+        assert_eq!(apply("split(',', 0)", "a,b,c".to_owned()).unwrap(), "a");
+        assert_eq!(apply("split(\",\", -1)", "a,b,c".to_owned()).unwrap(), "c");
+        assert_eq!(apply("split(::, 1)", "a::b".to_owned()).unwrap(), "b");
+        assert_eq!(apply("split(',', 0)", String::new()).unwrap(), "");
+    }
+
+    #[test]
+    fn should_reject_split_index_out_of_range() {
+        for index in ["3", "-4"] {
+            let error = apply(&format!("split(',', {index})"), "a,b,c".to_owned()).unwrap_err();
+            assert_eq!(error, format!("split: Index {index} is out of range for 3 elements"));
+        }
+        assert!(apply("split(',', x)", "a".to_owned()).unwrap_err().contains("Expected index"));
+        assert!(apply("split('', 0)", "a".to_owned()).unwrap_err().contains("cannot be empty"));
+        assert!(apply("split(',')", "a".to_owned()).unwrap_err().contains("Expected separator and index"));
+    }
+
+    #[test]
+    fn should_expand_leading_tilde_only() {
+        if let Some(home) = home_dir() {
+            assert_eq!(expand_tilde("~"), home);
+            assert_eq!(expand_tilde("~/.config"), format!("{home}/.config"));
+            assert_eq!(apply("expand_tilde", "~\\dir".to_owned()).unwrap(), format!("{home}\\dir"));
+        }
+        assert_eq!(expand_tilde("~user/.config"), "~user/.config");
+        assert_eq!(expand_tilde("dir/~"), "dir/~");
+    }
+
+    #[test]
+    fn should_reject_malformed_modifiers() {
+        assert!(apply("unknown", "a".to_owned()).unwrap_err().starts_with("Unknown modifier 'unknown'"));
+        assert!(apply("path(1)", "a".to_owned()).unwrap_err().contains("does not accept arguments"));
+        assert!(apply("split(',', 0", "a".to_owned()).is_err());
+        assert!(apply("split(',', 0) path", "a".to_owned()).unwrap_err().contains("Expected ':'"));
+    }
+
+    #[cfg(feature = "unicode")]
+    #[test]
+    fn should_normalize_unicode_form() {
+        let decomposed = "e\u{301}".to_owned();
+        assert_eq!(apply("nfc", decomposed.clone()).unwrap(), "\u{e9}");
+        assert_eq!(apply("nfkc", "\u{fb01}".to_owned()).unwrap(), "fi");
+        //Compatibility characters are kept by canonical form
+        assert_eq!(apply("nfc", "\u{fb01}".to_owned()).unwrap(), "\u{fb01}");
+        assert_eq!(apply("nfc:nfkc", decomposed).unwrap(), "\u{e9}");
+    }
+
+    #[cfg(not(feature = "unicode"))]
+    #[test]
+    fn should_require_unicode_feature_for_normalization() {
+        assert_eq!(apply("nfc", "a".to_owned()).unwrap_err(), "nfc: Modifier requires `unicode` feature");
+    }
+}
@@ -0,0 +1,177 @@
+//! References to external secret stores within env file values
+//!
+//! Resolved when env file is loaded, using CLI of corresponding store or AWS SDK.
+
+#[cfg(any(feature = "vault", feature = "1password"))]
+use crate::command;
+
+#[cfg(feature = "vault")]
+//`vault:secret/path#field`, resolved with `vault kv get`, using `VAULT_ADDR` and `VAULT_TOKEN`
+fn vault(reference: &str) -> Result<String, String> {
+    const DEFAULT_FIELD: &str = "value";
+
+    let (path, field) = match reference.split_once('#') {
+        Some((path, field)) => (path, field),
+        None => (reference, DEFAULT_FIELD),
+    };
+    if path.is_empty() || field.is_empty() {
+        return Err(format!("Invalid vault reference 'vault:{reference}'"));
+    }
+
+    let program = std::env::var("ENV_SMART_VAULT").unwrap_or_else(|_| "vault".to_owned());
+    let field = format!("-field={field}");
+    command::run(&program, ["kv", "get", field.as_str(), path], None).map(|value| value.trim_end_matches('\n').to_owned())
+}
+
+#[cfg(feature = "aws")]
+//Runs request of AWS SDK, using its standard credentials and region resolution
+//
+//Macro is synchronous, so request is completed on runtime created for it.
+fn aws<F: core::future::Future<Output = Result<String, String>>>(request: impl FnOnce(aws_config::SdkConfig) -> F) -> Result<String, String> {
+    let runtime = match tokio::runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(error) => return Err(format!("Cannot start runtime of AWS SDK: {error}")),
+    };
+    runtime.block_on(async {
+        let config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+        request(config).await
+    })
+}
+
+#[cfg(feature = "aws")]
+//`aws-ssm:/my/param`, decrypting `SecureString` parameters
+fn aws_ssm(name: &str) -> Result<String, String> {
+    if name.is_empty() {
+        return Err("Invalid AWS SSM reference 'aws-ssm:'".to_owned());
+    }
+
+    aws(|config| async move {
+        let client = aws_sdk_ssm::Client::new(&config);
+        match client.get_parameter().name(name).with_decryption(true).send().await {
+            Ok(output) => output.parameter.and_then(|parameter| parameter.value).ok_or_else(|| format!("AWS SSM parameter '{name}' has no value")),
+            Err(error) => Err(format!("Cannot get AWS SSM parameter '{name}': {}", aws_sdk_ssm::error::DisplayErrorContext(error))),
+        }
+    })
+}
+
+#[cfg(feature = "aws")]
+//`aws-sm:my-secret`, which must be text secret
+fn aws_secrets_manager(id: &str) -> Result<String, String> {
+    if id.is_empty() {
+        return Err("Invalid AWS Secrets Manager reference 'aws-sm:'".to_owned());
+    }
+
+    aws(|config| async move {
+        let client = aws_sdk_secretsmanager::Client::new(&config);
+        match client.get_secret_value().secret_id(id).send().await {
+            Ok(output) => output.secret_string.ok_or_else(|| format!("AWS secret '{id}' is not text")),
+            Err(error) => Err(format!("Cannot get AWS secret '{id}': {}", aws_sdk_secretsmanager::error::DisplayErrorContext(error))),
+        }
+    })
+}
+
+#[cfg(feature = "1password")]
+//`op://vault/item/field`, resolved with `op read`, using signed in account or `OP_SERVICE_ACCOUNT_TOKEN`
+fn one_password(reference: &str) -> Result<String, String> {
+    let program = std::env::var("ENV_SMART_OP").unwrap_or_else(|_| "op".to_owned());
+    command::run(&program, ["read", "--no-newline", reference], None)
+}
+
+///Resolves value if it is reference, returning `None` otherwise
+pub fn resolve(value: &str) -> Result<Option<String>, String> {
+    #[cfg(feature = "vault")]
+    if let Some(reference) = value.strip_prefix("vault:") {
+        return vault(reference).map(Some);
+    }
+
+    #[cfg(feature = "aws")]
+    if let Some(name) = value.strip_prefix("aws-ssm:") {
+        return aws_ssm(name).map(Some);
+    }
+
+    #[cfg(feature = "aws")]
+    if let Some(id) = value.strip_prefix("aws-sm:") {
+        return aws_secrets_manager(id).map(Some);
+    }
+
+    #[cfg(feature = "1password")]
+    if value.starts_with("op://") {
+        return one_password(value).map(Some);
+    }
+
+    Ok(None)
+}
+
+#[cfg(all(test, feature = "aws"))]
+mod tests {
+    use super::*;
+
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    //Serves `responses` as AWS endpoint, returning received requests
+    fn serve(responses: &'static [&'static str]) -> (String, std::thread::JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        let server = std::thread::spawn(move || {
+            let mut requests = Vec::new();
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request = String::new();
+                let mut len = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some((name, value)) = line.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            len = value.trim().parse().unwrap();
+                        }
+                    }
+                    request.push_str(&line);
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                let mut body = vec![0; len];
+                reader.read_exact(&mut body).unwrap();
+                request.push_str(&String::from_utf8(body).unwrap());
+                requests.push(request);
+
+                let reply = format!("HTTP/1.1 200 OK\r\nContent-Type: application/x-amz-json-1.1\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{response}", response.len());
+                reader.get_mut().write_all(reply.as_bytes()).unwrap();
+            }
+            requests
+        });
+        (endpoint, server)
+    }
+
+    #[test]
+    fn should_resolve_aws_references_with_sdk() {
+        let (endpoint, server) = serve(&[
+            r#"{"Parameter":{"Name":"/app/db","Type":"SecureString","Value":"postgres://db"}}"#,
+            r#"{"Name":"app/token","SecretString":"token-value"}"#,
+        ]);
+        std::env::set_var("AWS_ENDPOINT_URL", &endpoint);
+        std::env::set_var("AWS_REGION", "us-east-1");
+        std::env::set_var("AWS_ACCESS_KEY_ID", "AKIDTEST");
+        std::env::set_var("AWS_SECRET_ACCESS_KEY", "test");
+        std::env::set_var("AWS_EC2_METADATA_DISABLED", "true");
+        std::env::set_var("AWS_CONFIG_FILE", "/nonexistent/env-smart/config");
+        std::env::set_var("AWS_SHARED_CREDENTIALS_FILE", "/nonexistent/env-smart/credentials");
+
+        assert_eq!(resolve("aws-ssm:/app/db").unwrap().as_deref(), Some("postgres://db"));
+        assert_eq!(resolve("aws-sm:app/token").unwrap().as_deref(), Some("token-value"));
+        assert_eq!(resolve("plain value").unwrap(), None);
+        assert!(resolve("aws-ssm:").is_err());
+        assert!(resolve("aws-sm:").is_err());
+
+        let requests = server.join().unwrap();
+        let ssm = requests[0].to_lowercase();
+        assert!(ssm.contains("x-amz-target: amazonssm.getparameter"), "{}", requests[0]);
+        assert!(requests[0].contains(r#""Name":"/app/db""#) && requests[0].contains(r#""WithDecryption":true"#), "{}", requests[0]);
+        let sm = requests[1].to_lowercase();
+        assert!(sm.contains("x-amz-target: secretsmanager.getsecretvalue"), "{}", requests[1]);
+        assert!(requests[1].contains(r#""SecretId":"app/token""#), "{}", requests[1]);
+    }
+}
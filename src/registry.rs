@@ -0,0 +1,65 @@
+//! Environment variables persisted in Windows registry
+//!
+//! Read using `reg query`, so values set by installers or group policy are visible even if process was started before they were.
+
+use crate::command;
+
+const USER: &str = "HKCU\\Environment";
+const SYSTEM: &str = "HKLM\\SYSTEM\\CurrentControlSet\\Control\\Session Manager\\Environment";
+
+//Replaces `%NAME%` references of `REG_EXPAND_SZ` value, keeping unknown ones as is
+fn expand(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('%') {
+        let tail = &rest[start + 1..];
+        let end = match tail.find('%') {
+            Some(end) => end,
+            None => break,
+        };
+        result.push_str(&rest[..start]);
+        match std::env::var(&tail[..end]) {
+            Ok(value) if end > 0 => result.push_str(&value),
+            _ => {
+                result.push('%');
+                result.push_str(&tail[..end]);
+                result.push('%');
+            },
+        }
+        rest = &tail[end + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+//Parses `NAME    TYPE    VALUE` lines of `reg query` output
+fn parse(output: &str) -> Vec<(String, String)> {
+    output.lines().filter_map(|line| {
+        let mut parts = line.trim_start().splitn(3, "    ");
+        let (name, kind) = (parts.next()?, parts.next()?);
+        let value = parts.next().unwrap_or_default().trim_end_matches('\r');
+        match kind {
+            "REG_SZ" => Some((name.to_owned(), value.to_owned())),
+            "REG_EXPAND_SZ" => Some((name.to_owned(), expand(value))),
+            _ => None,
+        }
+    }).collect()
+}
+
+///Loads variables of current user followed by system ones, so that user's take priority
+///
+///Returns nothing if build doesn't run on Windows
+pub fn load() -> Result<Vec<(String, String)>, String> {
+    if !cfg!(windows) {
+        return Ok(Vec::new());
+    }
+
+    let mut result = Vec::new();
+    for key in [USER, SYSTEM] {
+        match command::run("reg", ["query", key], None) {
+            Ok(output) => result.extend(parse(&output)),
+            Err(error) => return Err(format!("Cannot read registry '{key}': {error}")),
+        }
+    }
+    Ok(result)
+}
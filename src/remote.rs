@@ -0,0 +1,60 @@
+//! Remote env file, fetched over HTTPS
+//!
+//! Downloaded using `curl` CLI (or program specified by `ENV_SMART_CURL`) from URL specified by `ENV_SMART_REMOTE`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::{command, dotenv};
+
+pub const URL: &str = "ENV_SMART_REMOTE";
+const PROGRAM: &str = "ENV_SMART_CURL";
+const TIMEOUT: &str = "ENV_SMART_REMOTE_TIMEOUT";
+//Seconds
+const DEFAULT_TIMEOUT: &str = "5";
+//Same server can expand macros of multiple crates, which should not fetch document each time
+const CACHE_TTL: Duration = Duration::from_secs(60);
+
+static CACHE: Mutex<Option<HashMap<String, (Instant, String)>>> = Mutex::new(None);
+
+///Returns URL of remote env file, if it is configured
+pub fn url() -> Option<String> {
+    std::env::var(URL).ok().filter(|url| !url.is_empty())
+}
+
+fn download(url: &str) -> Result<String, String> {
+    let mut cache = match CACHE.lock() {
+        Ok(cache) => cache,
+        Err(error) => error.into_inner(),
+    };
+    let cache = cache.get_or_insert_with(HashMap::new);
+    if let Some((fetched, text)) = cache.get(url) {
+        if fetched.elapsed() < CACHE_TTL {
+            return Ok(text.clone());
+        }
+    }
+
+    let timeout = std::env::var(TIMEOUT).unwrap_or_else(|_| DEFAULT_TIMEOUT.to_owned());
+    if timeout.parse::<f64>().map_or(true, |timeout| timeout <= 0.0) {
+        return Err(format!("{TIMEOUT}: Expected positive number of seconds, got '{timeout}'"));
+    }
+    let program = std::env::var(PROGRAM).unwrap_or_else(|_| "curl".to_owned());
+    //Redirects are not allowed to downgrade to plain HTTP
+    let args = ["--silent", "--show-error", "--fail", "--location", "--proto", "=https", "--proto-redir", "=https", "--max-time", &timeout, url];
+    let text = command::run(&program, args, None)?;
+    cache.insert(url.to_owned(), (Instant::now(), text.clone()));
+    Ok(text)
+}
+
+///Fetches and parses remote env file
+pub fn parse(url: &str, options: &dotenv::Options) -> Result<Option<Vec<dotenv::Entry>>, String> {
+    if !url.starts_with("https://") {
+        return Err(format!("{URL}: Expected https:// URL, got '{url}'"));
+    }
+
+    match download(url) {
+        Ok(text) => dotenv::parse_str(url, &text, options).map(Some).map_err(String::from),
+        Err(error) => Err(format!("{url}: Cannot fetch: {error}")),
+    }
+}
@@ -0,0 +1,132 @@
+use core::fmt;
+
+const REDACTED: &str = "«redacted»";
+//Patterns are matched against upper case key
+const PATTERNS: &[&str] = &[
+    "TOKEN",
+    "SECRET",
+    "PASSWORD",
+    "*_TOKEN",
+    "*_SECRET",
+    "*_PASSWORD",
+    "*_API_KEY",
+];
+
+///Returns whether `key` looks like it holds secret value
+///
+///`extra` patterns are expected to be upper case
+pub fn is_secret(key: &str, extra: &[String]) -> bool {
+    let key = key.to_ascii_uppercase();
+    PATTERNS.iter().copied().chain(extra.iter().map(String::as_str)).any(|pattern| crate::glob_match(pattern, &key))
+}
+
+///Returns patterns, which treat every name as secret
+pub fn any_name() -> Vec<String> {
+    vec!["*".to_owned()]
+}
+
+//Known prefixes of AWS access key ids
+const AWS_KEY_PREFIXES: &[&str] = &["AKIA", "ASIA", "AGPA", "AIDA", "AROA", "ANPA", "ANVA", "AIPA"];
+const AWS_KEY_LEN: usize = 20;
+const ENTROPY_MIN_LEN: usize = 24;
+const ENTROPY_THRESHOLD: f64 = 4.2;
+
+fn is_aws_key(value: &str) -> bool {
+    value.len() == AWS_KEY_LEN
+        && AWS_KEY_PREFIXES.iter().any(|prefix| value.starts_with(prefix))
+        && value.bytes().all(|byt| byt.is_ascii_uppercase() || byt.is_ascii_digit())
+}
+
+//Shannon entropy in bits per character
+fn entropy(value: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for byt in value.bytes() {
+        counts[byt as usize] += 1;
+    }
+
+    let len = value.len() as f64;
+    counts.iter().filter(|count| **count > 0).map(|count| {
+        let freq = *count as f64 / len;
+        -freq * freq.log2()
+    }).sum()
+}
+
+fn is_high_entropy(value: &str) -> bool {
+    value.len() >= ENTROPY_MIN_LEN
+        && !value.bytes().any(|byt| byt.is_ascii_whitespace())
+        && entropy(value) >= ENTROPY_THRESHOLD
+}
+
+///Checks whether variable looks like secret, returning reason if so
+pub fn detect(key: &str, value: &str, extra: &[String]) -> Option<&'static str> {
+    if is_secret(key, extra) {
+        Some("secret-like name")
+    } else if is_aws_key(value) {
+        Some("AWS access key id")
+    } else if is_high_entropy(value) {
+        Some("high entropy value")
+    } else {
+        None
+    }
+}
+
+///Displays value of variable unless its key looks like secret
+pub struct Redact<'a> {
+    key: &'a str,
+    value: &'a str,
+    extra: &'a [String],
+}
+
+impl<'a> Redact<'a> {
+    #[inline(always)]
+    pub fn new(key: &'a str, value: &'a str, extra: &'a [String]) -> Self {
+        Self {
+            key,
+            value,
+            extra,
+        }
+    }
+
+    ///Displays resolved value of macro input, unless input refers to secret-like variable
+    pub fn input(input: &'a str, value: &'a str, extra: &'a [String]) -> Self {
+        let mut names = crate::format::segments(input).into_iter().filter_map(|segment| match segment {
+            //Modifiers are not part of name
+            crate::format::Segment::Name(name) => name.split(':').next(),
+            crate::format::Segment::Plain(_) => None,
+        });
+        let key = names.find(|name| is_secret(name, extra)).unwrap_or(input);
+        Self::new(key, value, extra)
+    }
+}
+
+impl fmt::Display for Redact<'_> {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if is_secret(self.key, self.extra) {
+            fmt.write_str(REDACTED)
+        } else {
+            fmt.write_str(self.value)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_redact_any_name() {
+        let patterns = any_name();
+        assert_eq!(Redact::new("SIGNING", "hunter2", &patterns).to_string(), REDACTED);
+        assert_eq!(Redact::new("SIGNING", "hunter2", &[]).to_string(), "hunter2");
+        assert_eq!(Redact::new("API_TOKEN", "hunter2", &[]).to_string(), REDACTED);
+    }
+
+    #[test]
+    fn should_redact_input_with_secret_name() {
+        assert_eq!(Redact::input("API_TOKEN", "hunter2", &[]).to_string(), REDACTED);
+        assert_eq!(Redact::input("{HOST}:{API_TOKEN:trim}", "hunter2", &[]).to_string(), REDACTED);
+        assert_eq!(Redact::input("{HOST}:{PORT}", "localhost:80", &[]).to_string(), "localhost:80");
+        assert_eq!(Redact::input("{SIGNING}", "hunter2", &["SIGN*".to_owned()]).to_string(), REDACTED);
+    }
+}
@@ -0,0 +1,55 @@
+//! [SOPS](https://github.com/getsops/sops) encrypted env files
+//!
+//! Decrypted using `sops` CLI (or program specified by `ENV_SMART_SOPS`) with locally configured keys.
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+
+use crate::{command, dotenv};
+
+const PROGRAM: &str = "ENV_SMART_SOPS";
+
+#[derive(Debug, Clone, Copy)]
+pub enum Format {
+    Dotenv,
+    Yaml,
+}
+
+impl Format {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dotenv => "dotenv",
+            Self::Yaml => "yaml",
+        }
+    }
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+///Returns paths of encrypted versions of env file
+pub fn encrypted_paths(path: &Path) -> [(PathBuf, Format); 3] {
+    [
+        (with_suffix(path, ".sops"), Format::Dotenv),
+        (with_suffix(path, ".sops.yaml"), Format::Yaml),
+        (with_suffix(path, ".sops.yml"), Format::Yaml),
+    ]
+}
+
+///Decrypts and parses file, returning `None` if it doesn't exist
+pub fn parse_file(path: &Path, format: Format, options: &dotenv::Options) -> Result<Option<Vec<dotenv::Entry>>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let name = path.display().to_string();
+    let program = std::env::var(PROGRAM).unwrap_or_else(|_| "sops".to_owned());
+    let args = [OsStr::new("--decrypt"), "--input-type".as_ref(), format.as_str().as_ref(), "--output-type".as_ref(), "dotenv".as_ref(), path.as_os_str()];
+    match command::run(&program, args, None) {
+        Ok(text) => dotenv::parse_str(&name, &text, options).map(Some).map_err(String::from),
+        Err(error) => Err(format!("{name}: Cannot decrypt: {error}")),
+    }
+}
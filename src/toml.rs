@@ -0,0 +1,608 @@
+//! Minimal TOML parser
+//!
+//! Supports tables, arrays of tables, dotted and quoted keys, strings, integers, floats, booleans, arrays and inline tables.
+//! Dates are kept as plain strings.
+
+use std::collections::BTreeMap;
+
+pub type Table = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+    Array(Vec<Value>),
+    Table(Table),
+}
+
+impl Value {
+    #[inline]
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(value) => Some(value.as_str()),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Self::Boolean(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_table(&self) -> Option<&Table> {
+        match self {
+            Self::Table(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    #[inline]
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Self::Array(value) => Some(value.as_slice()),
+            _ => None,
+        }
+    }
+
+    ///Returns textual representation of scalar value
+    pub fn to_scalar_string(&self) -> Option<String> {
+        match self {
+            Self::String(value) => Some(value.clone()),
+            Self::Integer(value) => Some(value.to_string()),
+            Self::Float(value) => Some(value.to_string()),
+            Self::Boolean(value) => Some(value.to_string()),
+            Self::Array(_) | Self::Table(_) => None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Integer(_) => "integer",
+            Self::Float(_) => "float",
+            Self::Boolean(_) => "boolean",
+            Self::Array(_) => "array",
+            Self::Table(_) => "table",
+        }
+    }
+}
+
+///Gets value by dotted path of keys
+pub fn get<'a>(table: &'a Table, path: &[&str]) -> Option<&'a Value> {
+    let (last, path) = path.split_last()?;
+    let mut table = table;
+    for key in path {
+        table = table.get(*key)?.as_table()?;
+    }
+    table.get(*last)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn line(&self) -> usize {
+        self.text[..self.pos].matches('\n').count() + 1
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, String> {
+        Err(format!("line {}: {message}", self.line()))
+    }
+
+    #[inline]
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    #[inline]
+    fn starts_with(&self, prefix: &str) -> bool {
+        self.text[self.pos..].starts_with(prefix)
+    }
+
+    #[inline]
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.bump() {
+            Some(ch) if ch == expected => Ok(()),
+            Some(ch) => self.error(&format!("Expected '{expected}', got '{ch}'")),
+            None => self.error(&format!("Expected '{expected}', got end of file")),
+        }
+    }
+
+    fn skip_ws(&mut self) {
+        while let Some(' ' | '\t') = self.peek() {
+            self.bump();
+        }
+    }
+
+    fn skip_comment(&mut self) {
+        if let Some('#') = self.peek() {
+            while let Some(ch) = self.peek() {
+                if ch == '\n' {
+                    break;
+                }
+                self.bump();
+            }
+        }
+    }
+
+    //Skips whitespace, comments and new lines
+    fn skip_blank(&mut self) {
+        loop {
+            self.skip_ws();
+            self.skip_comment();
+            match self.peek() {
+                Some('\n' | '\r') => {
+                    self.bump();
+                },
+                _ => break,
+            }
+        }
+    }
+
+    fn end_of_line(&mut self) -> Result<(), String> {
+        self.skip_ws();
+        self.skip_comment();
+        match self.peek() {
+            None => Ok(()),
+            Some('\n') => {
+                self.bump();
+                Ok(())
+            },
+            Some('\r') if self.starts_with("\r\n") => {
+                self.pos += 2;
+                Ok(())
+            },
+            Some(ch) => self.error(&format!("Unexpected '{ch}' at the end of line")),
+        }
+    }
+
+    fn key_part(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some('"') => self.basic_string(),
+            Some('\'') => self.literal_string(),
+            _ => {
+                let start = self.pos;
+                while let Some(ch) = self.peek() {
+                    if ch.is_ascii_alphanumeric() || ch == '_' || ch == '-' {
+                        self.bump();
+                    } else {
+                        break;
+                    }
+                }
+                if start == self.pos {
+                    return self.error("Expected key");
+                }
+                Ok(self.text[start..self.pos].to_owned())
+            }
+        }
+    }
+
+    fn key(&mut self) -> Result<Vec<String>, String> {
+        let mut result = Vec::new();
+        loop {
+            self.skip_ws();
+            result.push(self.key_part()?);
+            self.skip_ws();
+            if let Some('.') = self.peek() {
+                self.bump();
+            } else {
+                break Ok(result);
+            }
+        }
+    }
+
+    fn escape(&mut self, out: &mut String) -> Result<(), String> {
+        let ch = match self.bump() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('r') => '\r',
+            Some('b') => '\u{8}',
+            Some('f') => '\u{c}',
+            Some('e') => '\u{1b}',
+            Some('"') => '"',
+            Some('\\') => '\\',
+            Some(kind @ ('u' | 'U')) => {
+                let len = if kind == 'u' { 4 } else { 8 };
+                let code = self.text.get(self.pos..self.pos + len).filter(|code| code.bytes().all(|byt| byt.is_ascii_hexdigit())).and_then(|code| u32::from_str_radix(code, 16).ok());
+                match code.and_then(char::from_u32) {
+                    Some(ch) => {
+                        self.pos += len;
+                        ch
+                    },
+                    None => return self.error("Invalid unicode escape"),
+                }
+            },
+            Some(ch) => return self.error(&format!("Invalid escape '\\{ch}'")),
+            None => return self.error("Unterminated string"),
+        };
+        out.push(ch);
+        Ok(())
+    }
+
+    fn basic_string(&mut self) -> Result<String, String> {
+        if self.starts_with("\"\"\"") {
+            return self.multiline_basic_string();
+        }
+
+        self.expect('"')?;
+        let mut result = String::new();
+        loop {
+            match self.bump() {
+                Some('"') => break Ok(result),
+                Some('\\') => self.escape(&mut result)?,
+                Some('\n') | None => break self.error("Unterminated string"),
+                Some(ch) => result.push(ch),
+            }
+        }
+    }
+
+    fn multiline_basic_string(&mut self) -> Result<String, String> {
+        self.pos += 3;
+        //Newline immediately after opening delimiter is trimmed
+        if self.starts_with("\r\n") {
+            self.pos += 2;
+        } else if self.starts_with("\n") {
+            self.pos += 1;
+        }
+
+        let mut result = String::new();
+        loop {
+            if self.starts_with("\"\"\"") {
+                self.pos += 3;
+                //Up to two quotes can be adjacent to closing delimiter
+                while let Some('"') = self.peek() {
+                    self.bump();
+                    result.push('"');
+                }
+                break Ok(result);
+            }
+
+            match self.bump() {
+                Some('\\') => match self.peek() {
+                    //Line ending backslash trims all whitespace up to next non-whitespace
+                    Some(' ' | '\t' | '\r' | '\n') => while let Some(' ' | '\t' | '\r' | '\n') = self.peek() {
+                        self.bump();
+                    },
+                    _ => self.escape(&mut result)?,
+                },
+                Some(ch) => result.push(ch),
+                None => break self.error("Unterminated string"),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> Result<String, String> {
+        if self.starts_with("'''") {
+            self.pos += 3;
+            if self.starts_with("\r\n") {
+                self.pos += 2;
+            } else if self.starts_with("\n") {
+                self.pos += 1;
+            }
+            return match self.text[self.pos..].find("'''") {
+                Some(end) => {
+                    let mut result = self.text[self.pos..self.pos + end].to_owned();
+                    self.pos += end + 3;
+                    while let Some('\'') = self.peek() {
+                        self.bump();
+                        result.push('\'');
+                    }
+                    Ok(result)
+                },
+                None => self.error("Unterminated string"),
+            }
+        }
+
+        self.expect('\'')?;
+        let start = self.pos;
+        loop {
+            match self.bump() {
+                Some('\'') => break Ok(self.text[start..self.pos - 1].to_owned()),
+                Some('\n') | None => break self.error("Unterminated string"),
+                Some(_) => (),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut result = Vec::new();
+        loop {
+            self.skip_blank();
+            if let Some(']') = self.peek() {
+                self.bump();
+                break Ok(Value::Array(result));
+            }
+
+            result.push(self.value()?);
+            self.skip_blank();
+            match self.bump() {
+                Some(',') => (),
+                Some(']') => break Ok(Value::Array(result)),
+                _ => break self.error("Expected ',' or ']' in array"),
+            }
+        }
+    }
+
+    fn inline_table(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut result = Table::new();
+        self.skip_ws();
+        if let Some('}') = self.peek() {
+            self.bump();
+            return Ok(Value::Table(result));
+        }
+
+        loop {
+            let key = self.key()?;
+            self.expect('=')?;
+            self.skip_ws();
+            let value = self.value()?;
+            insert(&mut result, &key, value).or_else(|error| self.error(&error))?;
+            self.skip_ws();
+            match self.bump() {
+                Some(',') => (),
+                Some('}') => break Ok(Value::Table(result)),
+                _ => break self.error("Expected ',' or '}' in inline table"),
+            }
+        }
+    }
+
+    fn scalar(&mut self) -> Result<Value, String> {
+        let start = self.pos;
+        while let Some(ch) = self.peek() {
+            if ch.is_ascii_alphanumeric() || matches!(ch, '_' | '-' | '+' | '.' | ':') {
+                self.bump();
+            } else if ch == ' ' && self.text[self.pos + 1..].starts_with(|ch: char| ch.is_ascii_digit()) && self.text[start..self.pos].contains('-') {
+                //Date and time separated with space
+                self.bump();
+            } else {
+                break;
+            }
+        }
+
+        let token = &self.text[start..self.pos];
+        match token {
+            "" => self.error("Expected value"),
+            "true" => Ok(Value::Boolean(true)),
+            "false" => Ok(Value::Boolean(false)),
+            "inf" | "+inf" => Ok(Value::Float(f64::INFINITY)),
+            "-inf" => Ok(Value::Float(f64::NEG_INFINITY)),
+            "nan" | "+nan" | "-nan" => Ok(Value::Float(f64::NAN)),
+            token => {
+                let number = token.replace('_', "");
+                let (radix, digits) = match number.get(..2) {
+                    Some("0x") => (16, &number[2..]),
+                    Some("0o") => (8, &number[2..]),
+                    Some("0b") => (2, &number[2..]),
+                    _ => (10, number.as_str()),
+                };
+
+                if let Ok(value) = i64::from_str_radix(digits, radix) {
+                    Ok(Value::Integer(value))
+                } else if let Ok(value) = number.parse::<f64>() {
+                    Ok(Value::Float(value))
+                } else if token.starts_with(|ch: char| ch.is_ascii_digit()) && (token.contains('-') || token.contains(':')) {
+                    Ok(Value::String(token.to_owned()))
+                } else {
+                    self.error(&format!("Unsupported value '{token}'"))
+                }
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek() {
+            Some('"') => self.basic_string().map(Value::String),
+            Some('\'') => self.literal_string().map(Value::String),
+            Some('[') => self.array(),
+            Some('{') => self.inline_table(),
+            _ => self.scalar(),
+        }
+    }
+}
+
+fn insert(table: &mut Table, key: &[String], value: Value) -> Result<(), String> {
+    let (last, path) = key.split_last().expect("non-empty key");
+    let table = table_at(table, path)?;
+    if table.contains_key(last) {
+        return Err(format!("Duplicate key '{}'", key.join(".")));
+    }
+    table.insert(last.clone(), value);
+    Ok(())
+}
+
+//Gets table by path, creating missing tables
+fn table_at<'a>(mut table: &'a mut Table, path: &[String]) -> Result<&'a mut Table, String> {
+    for key in path {
+        let value = table.entry(key.clone()).or_insert_with(|| Value::Table(Table::new()));
+        table = match value {
+            Value::Table(table) => table,
+            Value::Array(array) => match array.last_mut() {
+                Some(Value::Table(table)) => table,
+                _ => return Err(format!("Key '{key}' is not a table")),
+            },
+            _ => return Err(format!("Key '{key}' is not a table")),
+        };
+    }
+    Ok(table)
+}
+
+///Parses TOML document
+pub fn parse(text: &str) -> Result<Table, String> {
+    let mut parser = Parser {
+        text,
+        pos: 0,
+    };
+    let mut root = Table::new();
+    let mut current = Vec::new();
+
+    loop {
+        parser.skip_blank();
+        match parser.peek() {
+            None => break Ok(root),
+            Some('[') => {
+                parser.bump();
+                let is_array = if let Some('[') = parser.peek() {
+                    parser.bump();
+                    true
+                } else {
+                    false
+                };
+
+                let key = parser.key()?;
+                parser.expect(']')?;
+                if is_array {
+                    parser.expect(']')?;
+                    let (last, path) = key.split_last().expect("non-empty key");
+                    let table = table_at(&mut root, path).or_else(|error| parser.error(&error))?;
+                    match table.entry(last.clone()).or_insert_with(|| Value::Array(Vec::new())) {
+                        Value::Array(array) => array.push(Value::Table(Table::new())),
+                        _ => return parser.error(&format!("Key '{}' is not an array of tables", key.join("."))),
+                    }
+                } else {
+                    table_at(&mut root, &key).or_else(|error| parser.error(&error))?;
+                }
+                current = key;
+            },
+            Some(_) => {
+                let key = parser.key()?;
+                parser.expect('=')?;
+                parser.skip_ws();
+                let value = parser.value()?;
+                let table = table_at(&mut root, &current).or_else(|error| parser.error(&error))?;
+                insert(table, &key, value).or_else(|error| parser.error(&error))?;
+            }
+        }
+        parser.end_of_line()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(text: &str) -> Value {
+        let mut table = parse(&format!("key = {text}")).unwrap();
+        table.remove("key").unwrap()
+    }
+
+    fn string(value: &str) -> Value {
+        Value::String(value.to_owned())
+    }
+
+    #[test]
+    fn should_parse_strings() {
+        assert_eq!(value(r#""tab\tquote\"\\ \u00e9\U0001F600""#), string("tab\tquote\"\\ \u{e9}\u{1F600}"));
+        assert_eq!(value(r"'C:\path\no escape'"), string(r"C:\path\no escape"));
+        assert_eq!(value("\"\"\"\nfirst\n  second \\\n    joined\"\"\"\""), string("first\n  second joined\""));
+        assert_eq!(value("'''\nraw \\n\n'line'''"), string("raw \\n\n'line"));
+        assert_eq!(value("'''it''''"), string("it'"));
+        assert_eq!(value("\"\""), string(""));
+    }
+
+    #[test]
+    fn should_parse_scalars() {
+        assert_eq!(value("1_000"), Value::Integer(1000));
+        assert_eq!(value("-42"), Value::Integer(-42));
+        assert_eq!(value("0xff"), Value::Integer(255));
+        assert_eq!(value("0b101"), Value::Integer(5));
+        assert_eq!(value("1.5e3"), Value::Float(1500.0));
+        assert_eq!(value("-inf"), Value::Float(f64::NEG_INFINITY));
+        assert_eq!(value("true"), Value::Boolean(true));
+        assert_eq!(value("1979-05-27 07:32:00Z"), string("1979-05-27 07:32:00Z"));
+    }
+
+    #[test]
+    fn should_parse_arrays_and_inline_tables() {
+        let mut inner = Table::new();
+        inner.insert("name".to_owned(), string("a"));
+        inner.insert("enabled".to_owned(), Value::Boolean(false));
+        let expected = Value::Array(vec![Value::Integer(1), Value::Array(vec![string("x")]), Value::Table(inner)]);
+        assert_eq!(value("[\n  1, # one\n  ['x'],\n  { name = \"a\", enabled = false },\n]"), expected);
+        assert_eq!(value("{}"), Value::Table(Table::new()));
+    }
+
+    #[test]
+    fn should_parse_dotted_keys_and_headers() {
+        let table = parse("
+top.level = 1 # comment after value
+\"quoted.key\" = 2
+
+[package.metadata.env-smart]
+expand = true # enabled
+defaults.LOG = 'info'
+
+[[item]]
+name = 'first'
+[[item]]
+name = 'second'
+").unwrap();
+        assert_eq!(get(&table, &["top", "level"]), Some(&Value::Integer(1)));
+        assert_eq!(get(&table, &["quoted.key"]), Some(&Value::Integer(2)));
+        assert_eq!(get(&table, &["package", "metadata", "env-smart", "expand"]), Some(&Value::Boolean(true)));
+        assert_eq!(get(&table, &["package", "metadata", "env-smart", "defaults", "LOG"]), Some(&string("info")));
+        let items: Vec<_> = table["item"].as_array().unwrap().iter().map(|item| item.as_table().unwrap()["name"].clone()).collect();
+        assert_eq!(items, [string("first"), string("second")]);
+    }
+
+    #[test]
+    fn should_parse_crlf() {
+        let table = parse("[a]\r\nb = 1 # comment\r\n").unwrap();
+        assert_eq!(get(&table, &["a", "b"]), Some(&Value::Integer(1)));
+    }
+
+    #[test]
+    fn should_reject_malformed_document() {
+        let invalid = [
+            "key = ",
+            "key = \"unterminated",
+            "key = \"line\nbreak\"",
+            "key = '''unterminated",
+            "key = \"\\q\"",
+            "key = \"\\u12\"",
+            "key = \"\\u+041\"",
+            "key = \"\\uD800\"",
+            "key = [1, 2",
+            "key = [1 2]",
+            "key = { a = 1",
+            "key = { a = 1 b = 2 }",
+            "key = 1 2",
+            "key = nope",
+            "= 1",
+            "key 1",
+            "[table",
+            "[[array]",
+            "a = 1\na = 2",
+            "a = 1\n[a]",
+            "a = 1\na.b = 2",
+            "[a]\n[[a]]",
+        ];
+        for text in invalid {
+            assert!(parse(text).is_err(), "{:?} must be rejected", text);
+        }
+    }
+
+    #[test]
+    fn should_not_panic_on_truncated_document() {
+        let text = "[package]\nname = \"n\u{e9}\" # c\n[x.y]\nz = [1, { a = '''b''' }, \"\"\"\\\n c\"\"\", 0x1f, 1979-05-27 07:32:00]\n";
+        parse(text).unwrap();
+        for (idx, _) in text.char_indices() {
+            let _ = parse(&text[..idx]);
+        }
+    }
+}
@@ -0,0 +1,399 @@
+use core::fmt;
+use std::borrow::Cow;
+use std::collections::{hash_map, HashMap, HashSet};
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///Source of variable's value
+pub enum Source {
+    ///`.env` file
+    DotEnv,
+    ///Environment of process
+    Process,
+    #[cfg(feature = "windows-registry")]
+    ///Windows registry
+    Registry,
+    ///Default value from configuration file
+    Config,
+    ///Value bound by `NAME = "value"` argument of macro call
+    Call,
+}
+
+impl fmt::Display for Source {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DotEnv => fmt.write_str("dotenv"),
+            Self::Process => fmt.write_str("process"),
+            #[cfg(feature = "windows-registry")]
+            Self::Registry => fmt.write_str("registry"),
+            Self::Config => fmt.write_str("config"),
+            Self::Call => fmt.write_str("macro call"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+///Location of definition within env file
+pub struct Origin {
+    ///Name of file, which is shared by variables defined within it
+    pub file: Arc<str>,
+    pub line: usize,
+}
+
+impl fmt::Display for Origin {
+    #[inline(always)]
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.write_fmt(format_args!("{}:{}", self.file, self.line))
+    }
+}
+
+#[derive(Debug, Clone)]
+///Resolved variable
+pub struct Var {
+    ///Value, which is boxed without spare capacity as every variable of env files is kept
+    pub value: Box<str>,
+    pub source: Source,
+    ///Value of the other source, overridden by this one
+    pub shadowed: Option<String>,
+    ///Location of definition within env file, which is shadowed one if process overrides it
+    pub origin: Option<Origin>,
+    ///File, which content is value, as referenced by `@file:path`
+    pub value_file: Option<PathBuf>,
+}
+
+impl Var {
+    #[inline]
+    pub fn new(value: String, source: Source) -> Self {
+        Self {
+            value: value.into_boxed_str(),
+            source,
+            shadowed: None,
+            origin: None,
+            value_file: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+///Variable's value is not valid unicode
+pub struct NonUnicode;
+
+#[derive(Debug, Default)]
+///Variables resolver.
+///
+///Files are loaded once, while process environment is queried only for requested variables.
+pub struct Vars {
+    files: HashMap<Box<str>, Var>,
+    defaults: HashMap<String, Var>,
+    ///Arrays defined by files
+    arrays: HashMap<String, Vec<String>>,
+    ///Variables from cargo's `[env]`, with whether they are forced
+    cargo: HashMap<String, (String, bool)>,
+    ///Variables removed from process environment
+    unset: HashSet<String>,
+    case_insensitive: bool,
+    lossy: bool,
+    ///Whether process environment is consulted
+    process: bool,
+    ///Whether process environment overrides files
+    process_override: bool,
+}
+
+impl Vars {
+    pub fn new(case_insensitive: bool, lossy: bool) -> Self {
+        Self {
+            files: HashMap::new(),
+            defaults: HashMap::new(),
+            arrays: HashMap::new(),
+            cargo: HashMap::new(),
+            unset: HashSet::new(),
+            case_insensitive,
+            lossy,
+            process: false,
+            process_override: false,
+        }
+    }
+
+    #[inline]
+    ///Enables use of process environment
+    pub fn set_process(&mut self, process_override: bool) {
+        self.process = true;
+        self.process_override = process_override;
+    }
+
+    #[inline]
+    ///Returns whether process environment is consulted
+    pub fn has_process(&self) -> bool {
+        self.process
+    }
+
+    ///Returns name of variable as it is stored, which is upper case if names are case-insensitive
+    pub fn key<'a>(&self, key: &'a str) -> Cow<'a, str> {
+        if self.case_insensitive {
+            Cow::Owned(key.to_uppercase())
+        } else {
+            Cow::Borrowed(key)
+        }
+    }
+
+    #[inline]
+    ///Inserts variable from file, returning previous one
+    pub fn insert(&mut self, key: &str, var: Var) -> Option<Var> {
+        let key = self.key(key).into_owned().into_boxed_str();
+        self.files.insert(key, var)
+    }
+
+    #[inline]
+    ///Adds item to array, replacing array defined earlier if `replace` is set
+    pub fn push(&mut self, key: &str, value: String, replace: bool) {
+        let key = self.key(key).into_owned();
+        let array = self.arrays.entry(key).or_default();
+        if replace {
+            array.clear();
+        }
+        array.push(value);
+    }
+
+    #[inline]
+    ///Gets array defined by files
+    pub fn get_array(&self, key: &str) -> Option<&[String]> {
+        self.arrays.get(self.key(key).as_ref()).map(Vec::as_slice)
+    }
+
+    #[inline]
+    ///Removes variable defined by files so far and hides it from process environment
+    pub fn unset(&mut self, key: &str) {
+        let key = self.key(key).into_owned();
+        self.files.remove(key.as_str());
+        self.arrays.remove(&key);
+        self.unset.insert(key);
+    }
+
+    #[inline]
+    ///Inserts default value, unless it is already present
+    pub fn insert_default(&mut self, key: &str, var: Var) {
+        let key = self.key(key).into_owned();
+        if let hash_map::Entry::Vacant(vacant) = self.defaults.entry(key) {
+            vacant.insert(var);
+        }
+    }
+
+    #[inline]
+    ///Inserts variable from cargo's `[env]` as part of process environment, unless it is already present
+    pub fn insert_cargo(&mut self, key: &str, value: String, force: bool) {
+        let key = self.key(key).into_owned();
+        if let hash_map::Entry::Vacant(vacant) = self.cargo.entry(key) {
+            vacant.insert((value, force));
+        }
+    }
+
+    #[inline]
+    ///Gets variable defined by files only
+    pub fn get_file(&self, key: &str) -> Option<&Var> {
+        self.files.get(self.key(key).as_ref())
+    }
+
+    ///Returns variables defined by files, with arrays and names of removed variables, in arbitrary order
+    pub fn file_layer(&self) -> (impl Iterator<Item = (&str, &Var)>, impl Iterator<Item = (&str, &[String])>, impl Iterator<Item = &str>) {
+        (
+            self.files.iter().map(|(key, var)| (key.as_ref(), var)),
+            self.arrays.iter().map(|(key, array)| (key.as_str(), array.as_slice())),
+            self.unset.iter().map(String::as_str),
+        )
+    }
+
+    ///Returns names of variables defined by files, sorted by bytes of their names
+    pub fn file_keys(&self) -> Vec<&str> {
+        let mut keys: Vec<&str> = self.files.keys().map(AsRef::as_ref).collect();
+        keys.sort_unstable();
+        keys
+    }
+
+    ///Returns names of variables defined by any source, sorted by bytes of their names
+    ///
+    ///Order must not depend on hashing nor order of process environment, so that output of macros is reproducible.
+    pub fn keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.files.keys().map(ToString::to_string).chain(self.defaults.keys().cloned()).collect();
+        if self.process {
+            let process = std::env::vars_os().filter_map(|(key, _)| key.into_string().ok()).chain(self.cargo.keys().cloned());
+            keys.extend(process.map(|key| self.key(&key).into_owned()).filter(|key| !self.unset.contains(key)));
+        }
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+
+    fn convert(&self, value: OsString) -> Result<String, NonUnicode> {
+        match value.into_string() {
+            Ok(value) => Ok(value),
+            Err(value) if self.lossy => Ok(value.to_string_lossy().into_owned()),
+            Err(_) => Err(NonUnicode),
+        }
+    }
+
+    ///Looks up variable in process environment only
+    pub fn process(&self, key: &str) -> Result<Option<String>, NonUnicode> {
+        match self.process_os(key) {
+            Some(value) => self.convert(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    ///Looks up variable in process environment only, without requiring it to be unicode
+    pub fn process_os(&self, key: &str) -> Option<OsString> {
+        if self.unset.contains(self.key(key).as_ref()) {
+            return None;
+        }
+
+        let cargo = self.cargo.get(self.key(key).as_ref());
+        if let Some((value, true)) = cargo {
+            return Some(value.into());
+        }
+
+        let value = match std::env::var_os(key) {
+            Some(value) => Some(value),
+            //Names are compared without allocating upper case copy of every variable of environment
+            None if self.case_insensitive => {
+                let is_key = |name: &OsString| name.to_str().map_or(false, |name| name.chars().flat_map(char::to_uppercase).eq(key.chars().flat_map(char::to_uppercase)));
+                std::env::vars_os().find(|(name, _)| is_key(name)).map(|(_, value)| value)
+            },
+            None => None,
+        };
+        value.or_else(|| cargo.map(|(value, _)| value.into()))
+    }
+
+    ///Resolves variable from all sources
+    ///
+    ///Variable of files or defaults is borrowed, unless process environment provides or shadows its value.
+    pub fn get(&self, key: &str) -> Result<Option<Cow<'_, Var>>, NonUnicode> {
+        let file = self.get_file(key);
+        let process = if self.process {
+            match self.process(key) {
+                Ok(process) => process,
+                //Invalid value is only problem if it is to be used
+                Err(NonUnicode) if file.is_some() && !self.process_override => None,
+                Err(NonUnicode) => return Err(NonUnicode),
+            }
+        } else {
+            None
+        };
+
+        let var = match (file, process) {
+            (Some(file), Some(process)) if *file.value == process => Cow::Borrowed(file),
+            (Some(file), Some(process)) => Cow::Owned(if self.process_override {
+                Var {
+                    value: process.into_boxed_str(),
+                    source: Source::Process,
+                    shadowed: Some(file.value.to_string()),
+                    origin: file.origin.clone(),
+                    value_file: None,
+                }
+            } else {
+                Var {
+                    shadowed: Some(process),
+                    ..file.clone()
+                }
+            }),
+            (Some(file), None) => Cow::Borrowed(file),
+            (None, Some(process)) => Cow::Owned(Var::new(process, Source::Process)),
+            (None, None) => return Ok(self.defaults.get(self.key(key).as_ref()).map(Cow::Borrowed)),
+        };
+
+        Ok(Some(var))
+    }
+
+    #[inline]
+    ///Gets value of setting variable, ignoring invalid values
+    pub fn setting(&self, key: &str) -> Option<String> {
+        self.get(key).ok().flatten().map(|var| var.value.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_borrow_variable_of_files() {
+        let mut vars = Vars::new(false, false);
+        vars.insert("NAME", Var::new("file".to_owned(), Source::DotEnv));
+        vars.insert_default("LEVEL", Var::new("info".to_owned(), Source::Config));
+
+        assert!(matches!(vars.get("NAME"), Ok(Some(Cow::Borrowed(var))) if &*var.value == "file"));
+        assert!(matches!(vars.get("LEVEL"), Ok(Some(Cow::Borrowed(var))) if var.source == Source::Config));
+        assert!(matches!(vars.get("MISSING"), Ok(None)));
+    }
+
+    #[test]
+    fn should_own_variable_shadowed_by_process() {
+        std::env::set_var("ENV_SMART_TEST_SHADOWED", "process");
+        let mut vars = Vars::new(false, false);
+        vars.insert("ENV_SMART_TEST_SHADOWED", Var::new("file".to_owned(), Source::DotEnv));
+        vars.set_process(true);
+
+        let var = vars.get("ENV_SMART_TEST_SHADOWED").unwrap().unwrap();
+        assert!(matches!(var, Cow::Owned(_)));
+        assert_eq!((&*var.value, var.source, var.shadowed.as_deref()), ("process", Source::Process, Some("file")));
+    }
+
+    #[test]
+    fn should_lookup_names_case_insensitive() {
+        std::env::set_var("ENV_SMART_TEST_Mixed_Case", "process");
+        let mut vars = Vars::new(true, false);
+        vars.insert("app_name", Var::new("file".to_owned(), Source::DotEnv));
+        vars.push("list", "item".to_owned(), false);
+        vars.set_process(false);
+
+        assert_eq!(vars.get("APP_NAME").unwrap().map(|var| var.value.to_string()).as_deref(), Some("file"));
+        assert_eq!(&*vars.get_file("App_Name").unwrap().value, "file");
+        assert_eq!(vars.get_array("LIST"), Some(&["item".to_owned()][..]));
+        assert_eq!(vars.process("env_smart_test_mixed_case").unwrap().as_deref(), Some("process"));
+        assert_eq!(vars.key("app_name"), "APP_NAME");
+
+        let mut vars = Vars::new(false, false);
+        vars.insert("app_name", Var::new("file".to_owned(), Source::DotEnv));
+        assert!(vars.get_file("APP_NAME").is_none());
+        assert!(vars.process("env_smart_test_mixed_case").unwrap().is_none());
+    }
+
+    #[test]
+    fn should_unset_variables_of_every_source() {
+        std::env::set_var("ENV_SMART_TEST_UNSET", "process");
+        let mut vars = Vars::new(false, false);
+        vars.insert("ENV_SMART_TEST_UNSET", Var::new("file".to_owned(), Source::DotEnv));
+        vars.push("ENV_SMART_TEST_UNSET", "item".to_owned(), false);
+        vars.set_process(false);
+        vars.unset("ENV_SMART_TEST_UNSET");
+
+        assert!(vars.get("ENV_SMART_TEST_UNSET").unwrap().is_none());
+        assert!(vars.get_array("ENV_SMART_TEST_UNSET").is_none());
+        assert!(vars.process_os("ENV_SMART_TEST_UNSET").is_none());
+
+        //Variable can be defined again after removal
+        vars.insert("ENV_SMART_TEST_UNSET", Var::new("again".to_owned(), Source::DotEnv));
+        assert_eq!(&*vars.get("ENV_SMART_TEST_UNSET").unwrap().unwrap().value, "again");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn should_replace_invalid_unicode_in_lossy_mode() {
+        use std::os::unix::ffi::OsStrExt;
+
+        std::env::set_var("ENV_SMART_TEST_LOSSY", std::ffi::OsStr::from_bytes(b"a\xFFb"));
+        let mut vars = Vars::new(false, true);
+        vars.set_process(false);
+        assert_eq!(vars.process("ENV_SMART_TEST_LOSSY").unwrap().as_deref(), Some("a\u{FFFD}b"));
+        assert_eq!(&*vars.get("ENV_SMART_TEST_LOSSY").unwrap().unwrap().value, "a\u{FFFD}b");
+
+        let mut vars = Vars::new(false, false);
+        vars.set_process(false);
+        assert!(vars.process("ENV_SMART_TEST_LOSSY").is_err());
+        assert!(vars.get("ENV_SMART_TEST_LOSSY").is_err());
+        //Invalid value of process environment is ignored if file defines variable
+        vars.insert("ENV_SMART_TEST_LOSSY", Var::new("file".to_owned(), Source::DotEnv));
+        assert_eq!(&*vars.get("ENV_SMART_TEST_LOSSY").unwrap().unwrap().value, "file");
+    }
+}
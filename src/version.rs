@@ -0,0 +1,249 @@
+//! Semantic version comparison
+
+use core::cmp::Ordering;
+
+#[derive(Debug)]
+///Version, where missing components are treated as zero
+pub struct Version<'a> {
+    numbers: [u64; 3],
+    ///Number of specified components, which defines range of `^` and `~` requirements
+    len: usize,
+    pre: Option<&'a str>,
+}
+
+impl<'a> Version<'a> {
+    ///Parses `MAJOR[.MINOR[.PATCH]][-PRE][+BUILD]`, with optional `v` prefix
+    pub fn parse(text: &'a str) -> Result<Self, String> {
+        let version = text.trim();
+        let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+        //Build metadata doesn't affect precedence
+        let version = version.split('+').next().unwrap_or(version);
+        let (version, pre) = match version.split_once('-') {
+            Some((version, pre)) => (version, Some(pre)),
+            None => (version, None),
+        };
+
+        let mut numbers = [0; 3];
+        let mut len = 0;
+        let mut parts = version.split('.');
+        for number in numbers.iter_mut() {
+            match parts.next() {
+                Some(part) => match part.parse() {
+                    Ok(part) => *number = part,
+                    Err(_) => return Err(format!("'{text}' is not valid version")),
+                },
+                None => break,
+            }
+            len += 1;
+        }
+        if version.is_empty() || parts.next().is_some() || pre == Some("") {
+            return Err(format!("'{text}' is not valid version"));
+        }
+
+        Ok(Self {
+            numbers,
+            len,
+            pre,
+        })
+    }
+
+    //Returns exclusive upper bound of numbers compatible with requirement, same as cargo's `^` or `~` (if `minor_only`)
+    fn upper_bound(&self, minor_only: bool) -> [u64; 3] {
+        let idx = match minor_only {
+            true => (self.len - 1).min(1),
+            //Components up to first non-zero one must match
+            false => self.numbers[..self.len].iter().position(|number| *number != 0).unwrap_or(self.len - 1),
+        };
+        let mut bound = [0; 3];
+        bound[..idx].copy_from_slice(&self.numbers[..idx]);
+        bound[idx] = self.numbers[idx].saturating_add(1);
+        bound
+    }
+}
+
+fn compare_pre(left: &str, right: &str) -> Ordering {
+    let mut left = left.split('.');
+    let mut right = right.split('.');
+    loop {
+        let ordering = match (left.next(), right.next()) {
+            (Some(left), Some(right)) => match (left.parse::<u64>(), right.parse::<u64>()) {
+                (Ok(left), Ok(right)) => left.cmp(&right),
+                //Numeric identifiers have lower precedence
+                (Ok(_), Err(_)) => Ordering::Less,
+                (Err(_), Ok(_)) => Ordering::Greater,
+                (Err(_), Err(_)) => left.cmp(right),
+            },
+            (Some(_), None) => Ordering::Greater,
+            (None, Some(_)) => Ordering::Less,
+            (None, None) => return Ordering::Equal,
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+}
+
+impl Ord for Version<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.numbers.cmp(&other.numbers).then_with(|| match (self.pre, other.pre) {
+            (Some(left), Some(right)) => compare_pre(left, right),
+            //Pre-release precedes release
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        })
+    }
+}
+
+impl PartialEq for Version<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Version<'_> {}
+
+impl PartialOrd for Version<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+///Comparison operator
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    ///Compatible with requirement as cargo's default requirement: `^1.2` allows `1.x`, but `^0.2` only `0.2.x`
+    Caret,
+    ///Same major and minor version as requirement: `~1.2` allows `1.2.x`
+    Tilde,
+}
+
+impl Op {
+    ///Parses operator from its text
+    pub fn parse(text: &str) -> Option<Self> {
+        match text {
+            "==" | "=" => Some(Self::Eq),
+            "!=" => Some(Self::Ne),
+            "<" => Some(Self::Lt),
+            "<=" => Some(Self::Le),
+            ">" => Some(Self::Gt),
+            ">=" => Some(Self::Ge),
+            "^" => Some(Self::Caret),
+            "~" => Some(Self::Tilde),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Eq => "==",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Caret => "^",
+            Self::Tilde => "~",
+        }
+    }
+
+    ///Returns whether version satisfies operator with required version
+    pub fn matches(self, version: &Version<'_>, required: &Version<'_>) -> bool {
+        let ordering = version.cmp(required);
+        match self {
+            Self::Eq => ordering == Ordering::Equal,
+            Self::Ne => ordering != Ordering::Equal,
+            Self::Lt => ordering == Ordering::Less,
+            Self::Le => ordering != Ordering::Greater,
+            Self::Gt => ordering == Ordering::Greater,
+            Self::Ge => ordering != Ordering::Less,
+            //Pre-release of upper bound, such as `2.0.0-alpha` for `^1.2`, is not compatible either
+            Self::Caret => ordering != Ordering::Less && version.numbers < required.upper_bound(false),
+            Self::Tilde => ordering != Ordering::Less && version.numbers < required.upper_bound(true),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(version: &str, op: &str, required: &str) -> bool {
+        Op::parse(op).unwrap().matches(&Version::parse(version).unwrap(), &Version::parse(required).unwrap())
+    }
+
+    #[test]
+    fn should_compare_versions() {
+        let ordered = ["0.9.10", "1.0.0", "1.2", "1.2.1", "1.10.0", "2.0.0"];
+        for pair in ordered.windows(2) {
+            assert!(Version::parse(pair[0]).unwrap() < Version::parse(pair[1]).unwrap(), "{} < {}", pair[0], pair[1]);
+        }
+        assert_eq!(Version::parse("2.3").unwrap(), Version::parse("v2.3.0").unwrap());
+        assert_eq!(Version::parse("2").unwrap(), Version::parse("2.0.0+build.5").unwrap());
+    }
+
+    #[test]
+    fn should_order_pre_releases() {
+        //Example of semver specification
+        let ordered = ["1.0.0-alpha", "1.0.0-alpha.1", "1.0.0-alpha.beta", "1.0.0-beta", "1.0.0-beta.2", "1.0.0-beta.11", "1.0.0-rc.1", "1.0.0"];
+        for pair in ordered.windows(2) {
+            assert!(Version::parse(pair[0]).unwrap() < Version::parse(pair[1]).unwrap(), "{} < {}", pair[0], pair[1]);
+        }
+        assert!(Version::parse("1.0.0-rc.1").unwrap() > Version::parse("0.9.9").unwrap());
+    }
+
+    #[test]
+    fn should_reject_invalid_versions() {
+        for text in ["", "v", "1.", "1.2.3.4", "1.x", "-1", "1.0-", "1..2"] {
+            assert!(Version::parse(text).is_err(), "{:?} must be rejected", text);
+        }
+    }
+
+    #[test]
+    fn should_match_comparison_operators() {
+        assert!(matches("2.3.0", ">=", "2.3"));
+        assert!(matches("2.10", ">=", "2.3"));
+        assert!(!matches("2.3.0-rc.1", ">=", "2.3"));
+        assert!(matches("2.3.0", "==", "2.3"));
+        assert!(matches("2.3.0", "=", "2.3"));
+        assert!(matches("2.3.1", "!=", "2.3"));
+        assert!(matches("1.9", "<", "2.0.0-alpha"));
+        assert!(matches("2.0.0-alpha", "<=", "2.0.0-alpha"));
+        assert!(matches("2.0.1", ">", "2"));
+    }
+
+    #[test]
+    fn should_match_caret_requirements() {
+        assert!(matches("1.2.3", "^", "1.2.3"));
+        assert!(matches("1.9.0", "^", "1.2.3"));
+        assert!(!matches("1.2.2", "^", "1.2.3"));
+        assert!(!matches("2.0.0", "^", "1.2.3"));
+        assert!(!matches("2.0.0-alpha", "^", "1.2.3"));
+        assert!(matches("0.2.9", "^", "0.2.3"));
+        assert!(!matches("0.3.0", "^", "0.2.3"));
+        assert!(matches("0.0.3", "^", "0.0.3"));
+        assert!(!matches("0.0.4", "^", "0.0.3"));
+        assert!(matches("0.0.9", "^", "0.0"));
+        assert!(!matches("0.1.0", "^", "0.0"));
+        assert!(matches("0.9.0", "^", "0"));
+        assert!(!matches("1.0.0", "^", "0"));
+    }
+
+    #[test]
+    fn should_match_tilde_requirements() {
+        assert!(matches("1.2.9", "~", "1.2.3"));
+        assert!(!matches("1.3.0", "~", "1.2.3"));
+        assert!(!matches("1.2.2", "~", "1.2.3"));
+        assert!(matches("1.2.0", "~", "1.2"));
+        assert!(!matches("1.3.0", "~", "1.2"));
+        assert!(matches("1.9.0", "~", "1"));
+        assert!(!matches("2.0.0", "~", "1"));
+        assert!(matches("0.2.5", "~", "0.2"));
+    }
+}